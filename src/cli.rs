@@ -1,6 +1,15 @@
-use clap::Parser;
+use crate::file_helpers::DuckDBLimits;
+use crate::logging::LogFile;
+use crate::schedule::parse_schedule;
+use clap::{Parser, Subcommand, ValueEnum};
+use cron::Schedule;
 use directories::ProjectDirs;
-use std::path::PathBuf;
+use polars::prelude::QuoteStyle;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 
 // SELECT schema_name FROM information_schema.schemata;
 
@@ -15,6 +24,9 @@ use std::path::PathBuf;
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to config file
     #[clap(short, long)]
     config: Option<PathBuf>,
@@ -27,6 +39,10 @@ pub struct Cli {
     #[clap(next_help_heading = "Database Options")]
     pub database: DatabaseOptions,
 
+    #[command(flatten)]
+    #[clap(next_help_heading = "Export Options")]
+    pub export: ExportOptions,
+
     /// Limit the number of rows exported per table
     #[arg(long)]
     pub row_limit: Option<u32>,
@@ -34,6 +50,112 @@ pub struct Cli {
     /// Run as a service, periodically fetching data (seconds)
     #[arg(long)]
     pub delay: Option<u32>,
+
+    /// Run as a service on a cron schedule in local time, e.g. "0 0 2 * * *" for 2am daily
+    /// (fields: sec min hour day-of-month month day-of-week)
+    #[arg(long, conflicts_with = "delay", value_parser = parse_schedule)]
+    pub schedule: Option<Schedule>,
+
+    /// Print the estimated on-disk size of the export from catalog statistics and exit
+    #[arg(long)]
+    pub estimate_size: bool,
+
+    /// Log more detail, repeat for trace output (`RUST_LOG` takes precedence)
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less, `-q` hides progress and `-qq` only shows errors
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub quiet: u8,
+
+    /// How log lines are written, `json` writes one object per line with the
+    /// table, database and row counts as fields where they're known
+    #[arg(long, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    /// Also append log lines to this file, which is rotated once it reaches
+    /// `--log-max-size-mb`, e.g. for a long running `--delay` service
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Size in MiB the log file may grow to before it's renamed to `<PATH>.1`
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub log_max_size_mb: u64,
+
+    /// Number of rotated log files kept, the oldest is deleted
+    #[arg(long, default_value_t = 5)]
+    pub log_max_files: usize,
+
+    /// Only write log lines to `--log-file`, not to stderr
+    #[arg(long, requires = "log_file")]
+    pub no_console_log: bool,
+
+    /// Ratio of parquet size to the source table size used by `--estimate-size`
+    /// and `--check-disk-space`
+    #[arg(default_value_t = 0.25, long)]
+    pub compression_ratio_estimate: f64,
+
+    /// Free space in MiB the export directory's filesystem must have before exporting,
+    /// with `--check-disk-space` the estimate is used if it's larger
+    #[arg(long, value_name = "MB")]
+    pub min_free_mb: Option<u64>,
+
+    /// Check the export directory's filesystem has room for the export before exporting,
+    /// estimated from the source tables' sizes and `--compression-ratio-estimate`
+    #[arg(long)]
+    pub check_disk_space: bool,
+
+    /// Serve Prometheus metrics of the last run on http://ADDR/metrics, e.g. 0.0.0.0:9187.
+    /// Meant for watch mode (--delay or --schedule), the metrics update after each run
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Print the last N rows of each table (by primary key) instead of exporting
+    #[arg(long, value_name = "N")]
+    pub tail: Option<u32>,
+
+    /// Load the parquet and CSV files in DIR into the DuckDB database instead of exporting,
+    /// without connecting to any source database. Tables are named after the files
+    #[arg(long, value_name = "DIR")]
+    pub from_parquet: Option<PathBuf>,
+
+    /// Maximum number of databases `validate` connects to at once (default: all)
+    #[arg(long)]
+    pub startup_concurrency: Option<NonZeroUsize>,
+}
+
+/// Alternative actions, without a subcommand the configured databases are exported
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Rebuild the DuckDB database from previously exported parquet files,
+    /// without connecting to any source database
+    BuildDuckdb {
+        /// Export directory containing one subdirectory of parquet files per schema
+        export_directory: PathBuf,
+    },
+    /// Print the tables of each database that would be exported, after
+    /// `include_tables` and `exclude_tables` are applied
+    ListTables {
+        /// Print a JSON object of table names keyed by database name instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that every configured database is reachable and its tables can be
+    /// listed, without exporting anything
+    Validate,
+    /// Recompute the SHA-256 of every file in a `manifest.json` and report any
+    /// that are missing or have changed, without connecting to any source database
+    VerifyManifest {
+        /// The manifest written by a previous export
+        manifest: PathBuf,
+    },
+    /// Compare each database with the one named by its `verify_against` setting,
+    /// reporting missing tables and differing row counts
+    Verify {
+        /// Also compare an order-independent hash of every row (reads both tables in full)
+        #[arg(long)]
+        checksum: bool,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -49,24 +171,395 @@ pub struct DatabaseOptions {
     /// Custom separator to use instead of schemas in database
     #[arg(long)]
     separator: Option<String>,
+
+    /// Copy SQLite tables straight into DuckDB (via the sqlite extension) instead of through parquet
+    /// (tables with a filter, rename, masked or dropped columns etc. still go through parquet,
+    /// as does every table if the extension can't be loaded or installed, and other
+    /// database types, both with a warning)
+    #[arg(long)]
+    duckdb_direct_sqlite: bool,
+
+    /// Create DuckDB views over the parquet files instead of copying them into tables,
+    /// so the DuckDB file stays small and always shows the latest export
+    #[arg(long)]
+    duckdb_views: bool,
+
+    /// Most memory DuckDB may use while loading, e.g. `4GB` (default: DuckDB's, 80% of RAM)
+    #[arg(long)]
+    duckdb_memory_limit: Option<String>,
+
+    /// Threads DuckDB may use while loading (default: DuckDB's, one per CPU)
+    #[arg(long)]
+    duckdb_threads: Option<NonZeroUsize>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExportOptions {
+    /// Limit the rows exported from one table, e.g. `--table-limit notes=1000` (-1 for no limit),
+    /// repeatable, takes precedence over the config's `override_limits`
+    #[arg(long, value_name = "TABLE=ROWS", value_parser = parse_table_limit)]
+    pub table_limit: Vec<(String, Option<u32>)>,
+
+    /// Fill a `${name}` placeholder in the custom queries, e.g. `--param start=2024-01-01`,
+    /// repeatable, takes precedence over each query's `params`
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_param)]
+    pub param: Vec<(String, String)>,
+
+    /// Re-read each parquet file after writing and check the columns and row count match
+    #[arg(long)]
+    pub parquet_validate: bool,
+
+    /// After writing each parquet file, compare its row count with a `COUNT(*)` of the
+    /// source table and warn, or fail the table, if they differ
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "warn")]
+    pub verify_row_counts: Option<RowCountCheck>,
+
+    /// Also write the query plan of each custom query to `<name>.plan.txt`
+    #[arg(long)]
+    pub explain_custom_queries: bool,
+
+    /// Identifier used to tag this run, a UUID is generated if omitted
+    #[arg(long)]
+    pub run_id: Option<String>,
+
+    /// Add a `_run_id` column containing the run ID to every exported file
+    #[arg(long)]
+    pub run_id_column: bool,
+
+    /// Write column statistics for each table to `<table>.profile.json`
+    #[arg(long)]
+    pub profile: bool,
+
+    /// Write the column names, types and nullability of each table to `<table>.schema.json`
+    #[arg(long)]
+    pub export_schema: bool,
+
+    /// Write `CREATE TABLE` statements for the exported tables, in this SQL dialect,
+    /// to `schema.sql` in the export directory
+    #[arg(long, value_enum)]
+    pub ddl: Option<SqlDialect>,
+
+    /// Append the outcome of every table to this SQLite database (requires the duckdb feature)
+    #[arg(long)]
+    pub run_log: Option<PathBuf>,
+
+    /// Upload the exported files and the DuckDB database to this S3 bucket after each run,
+    /// using the default AWS credentials and region (requires the s3 feature)
+    #[arg(long)]
+    pub s3_bucket: Option<String>,
+
+    /// Prefix of the uploaded keys, e.g. `exports/nightly`
+    #[arg(long, requires = "s3_bucket")]
+    pub s3_prefix: Option<String>,
+
+    /// POST a JSON summary of each run to this URL, e.g. a chat or alerting webhook.
+    /// Server errors are retried, an unreachable webhook is logged but doesn't fail the export
+    #[arg(long)]
+    pub webhook_url: Option<String>,
+
+    /// How long each webhook request may take in seconds
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub webhook_timeout_secs: u64,
+
+    /// Also export views, like tables, databases may override this in the config
+    #[arg(long)]
+    pub include_views: bool,
+
+    /// Embed the source table, database, export time, run ID, tool version and row limit
+    /// in each parquet file's metadata
+    #[arg(long)]
+    pub parquet_metadata: bool,
+
+    /// Maximum number of rows in each parquet row group (default: polars' default)
+    #[arg(long, value_parser = parse_row_group_size)]
+    pub row_group_size: Option<usize>,
+
+    /// Stream parquet exports this many rows at a time to bound memory use,
+    /// only tables with a single integer primary key are read in batches
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub batch_size: Option<u32>,
+
+    /// Maximum number of tables exported at once, which limits the concurrent
+    /// queries against each source database (default: one per CPU)
+    #[arg(long)]
+    pub max_table_concurrency: Option<NonZeroUsize>,
+
+    /// Give up on a table or custom query whose query hasn't returned after this many
+    /// seconds, failing it rather than hanging the export (default: no timeout)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub query_timeout_secs: Option<u64>,
+
+    /// Maximum memory in MiB held by the tables being exported at once, estimated
+    /// from each table's row and column counts. Tables over the budget run alone
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub memory_budget_mb: Option<u64>,
+
+    /// Number of times to retry a table or custom query that fails with a
+    /// connection or network error
+    #[arg(default_value_t = 0, long)]
+    pub max_retries: u32,
+
+    /// Delay before the first retry in milliseconds, doubled for each retry after it
+    #[arg(default_value_t = 500, long)]
+    pub retry_base_delay_ms: u64,
+
+    /// Stop the whole run at the first failed table or custom query, without
+    /// building DuckDB for that database
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Delete exported files of tables and custom queries that no longer exist in the
+    /// source, so they aren't mistaken for current data
+    #[arg(long)]
+    pub prune_stale: bool,
+
+    /// Write each run into its own `YYYYMMDD-HHMMSS` directory underneath the export
+    /// directory, including the DuckDB file, rather than overwriting the last export
+    #[arg(long)]
+    pub snapshot: bool,
+
+    /// Point a `latest` symlink in the export directory at the newest snapshot
+    #[arg(long, requires = "snapshot")]
+    pub latest_symlink: bool,
+
+    /// Print which tables and custom queries would be exported, and where to,
+    /// without exporting any data or writing any files
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Show a progress bar of the tables exported from each database, only drawn
+    /// when stdout is a terminal and the `CI` environment variable isn't set
+    #[arg(long)]
+    pub progress: bool,
+
+    /// The file format tables are written in, DuckDB is only built from parquet,
+    /// `duckdb-only` loads each table straight into the DuckDB database without
+    /// writing any table files
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// String written for null values in CSV output (default: empty)
+    #[arg(long)]
+    pub csv_null_value: Option<String>,
+
+    /// Single ASCII character separating CSV fields (default: `,`)
+    #[arg(long)]
+    pub csv_delimiter: Option<char>,
+
+    /// When CSV fields are quoted (default: necessary)
+    #[arg(long, value_enum)]
+    pub csv_quote_style: Option<CsvQuoteStyle>,
+}
+
+/// The largest accepted `--row-group-size`, larger groups defeat the point of row groups
+const MAX_ROW_GROUP_SIZE: usize = 64 * 1024 * 1024;
+
+/// Parses `--row-group-size`, which must be between 1 and `MAX_ROW_GROUP_SIZE`
+fn parse_row_group_size(value: &str) -> Result<usize, String> {
+    let size: usize = value.parse().map_err(|e| format!("{e}"))?;
+    if !(1..=MAX_ROW_GROUP_SIZE).contains(&size) {
+        return Err(format!(
+            "row group size must be between 1 and {MAX_ROW_GROUP_SIZE}"
+        ));
+    }
+    Ok(size)
+}
+
+/// What `--verify-row-counts` does when a file and its source table differ
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowCountCheck {
+    /// Log a warning and keep the table
+    Warn,
+    /// Fail the table
+    Fail,
+}
+
+/// How `--log-format` writes log lines
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+/// The SQL dialect `--ddl` writes `CREATE TABLE` statements in
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    Postgres,
+    Duckdb,
+}
+
+/// The file format tables are exported as
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Parquet,
+    Csv,
+    /// Newline delimited JSON, one object per row
+    Json,
+    /// Only the DuckDB database, tables are staged as parquet and deleted once loaded,
+    /// so a database's parquet files still need room on disk while it's exported
+    DuckdbOnly,
+}
+
+impl OutputFormat {
+    /// The file extension used for this format, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "ndjson",
+            OutputFormat::DuckdbOnly => "parquet",
+        }
+    }
+
+    /// Whether tables are written as parquet, or loaded into DuckDB in their place for `DuckdbOnly`
+    pub fn is_parquet(&self) -> bool {
+        matches!(self, OutputFormat::Parquet | OutputFormat::DuckdbOnly)
+    }
+}
+
+/// When fields are quoted in CSV output, see `polars::prelude::QuoteStyle`
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum CsvQuoteStyle {
+    /// Only quote fields containing the delimiter, quotes or newlines
+    Necessary,
+    Always,
+    /// Quote every field that isn't a number
+    NonNumeric,
+    Never,
+}
+
+impl From<CsvQuoteStyle> for QuoteStyle {
+    fn from(style: CsvQuoteStyle) -> Self {
+        match style {
+            CsvQuoteStyle::Necessary => QuoteStyle::Necessary,
+            CsvQuoteStyle::Always => QuoteStyle::Always,
+            CsvQuoteStyle::NonNumeric => QuoteStyle::NonNumeric,
+            CsvQuoteStyle::Never => QuoteStyle::Never,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Generates a run ID if one was not provided on the command line
+    pub fn resolve_run_id(&mut self) {
+        self.run_id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string());
+    }
+
+    /// Returns the run ID, this is empty until `resolve_run_id` has been called
+    pub fn run_id(&self) -> &str {
+        self.run_id.as_deref().unwrap_or_default()
+    }
+
+    /// Merges the `--table-limit` overrides into a database's `override_limits`,
+    /// replacing the config's limit for any table named on the command line
+    pub fn merge_table_limits(
+        &self,
+        override_limits: Option<HashMap<String, Option<u32>>>,
+    ) -> Option<HashMap<String, Option<u32>>> {
+        if self.table_limit.is_empty() {
+            return override_limits;
+        }
+        let mut limits = override_limits.unwrap_or_default();
+        limits.extend(self.table_limit.iter().cloned());
+        Some(limits)
+    }
+}
+
+/// Parses a `--table-limit` of the form `table=rows`, where -1 rows means no limit
+fn parse_table_limit(value: &str) -> Result<(String, Option<u32>), String> {
+    let Some((table, rows)) = value.rsplit_once('=') else {
+        return Err(format!(
+            "expected TABLE=ROWS, e.g. notes=1000, got '{value}'"
+        ));
+    };
+    let table = table.trim();
+    if table.is_empty() {
+        return Err(format!("missing table name in '{value}'"));
+    }
+    let limit = match rows.trim() {
+        "-1" => None,
+        rows => Some(rows.parse::<u32>().map_err(|_| {
+            format!("row limit '{rows}' for table '{table}' must be a non-negative number or -1")
+        })?),
+    };
+    Ok((table.to_string(), limit))
+}
+
+/// Parses a `--param` of the form `name=value`, the value may be empty or contain `=`
+fn parse_param(value: &str) -> Result<(String, String), String> {
+    let Some((name, param)) = value.split_once('=') else {
+        return Err(format!(
+            "expected NAME=VALUE, e.g. start=2024-01-01, got '{value}'"
+        ));
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing parameter name in '{value}'"));
+    }
+    Ok((name.to_string(), param.to_string()))
 }
 
 #[derive(Debug, Clone)]
 pub struct DuckDBExportOptions {
+    /// Whether to build the DuckDB database, databases may override this in the config
+    pub include: bool,
     pub file_name: String,
     pub separator: Option<String>,
+    /// Copy SQLite sources directly into DuckDB, skipping the parquet files
+    pub direct_sqlite: bool,
+    /// Create views over the parquet files rather than tables
+    pub views: bool,
+    pub limits: DuckDBLimits,
+    /// SQL run in order once the tables are loaded, from the database's config
+    pub post_load_sql: Vec<String>,
+    /// A `.sql` file run after `post_load_sql`, from the database's config
+    pub post_load_sql_file: Option<PathBuf>,
 }
 
 impl From<&DatabaseOptions> for DuckDBExportOptions {
     fn from(opts: &DatabaseOptions) -> Self {
         Self {
+            include: opts.include_duckdb,
             file_name: opts.duckdb_file_name.clone(),
             separator: opts.separator.clone(),
+            direct_sqlite: opts.duckdb_direct_sqlite,
+            views: opts.duckdb_views,
+            limits: DuckDBLimits {
+                memory_limit: opts.duckdb_memory_limit.clone(),
+                threads: opts.duckdb_threads,
+            },
+            post_load_sql: Vec::new(),
+            post_load_sql_file: None,
         }
     }
 }
 
 impl Cli {
+    /// The log level selected by `--verbose` and `--quiet`, `info` by default
+    pub fn log_level(&self) -> log::LevelFilter {
+        match (self.verbose, self.quiet) {
+            (0, 0) => log::LevelFilter::Info,
+            (0, 1) => log::LevelFilter::Warn,
+            (0, _) => log::LevelFilter::Error,
+            (1, _) => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+
+    /// The `--log-file` to write, if any
+    pub fn log_file(&self) -> Option<LogFile> {
+        self.log_file.as_ref().map(|path| LogFile {
+            path: path.clone(),
+            max_bytes: self.log_max_size_mb.saturating_mul(1024 * 1024),
+            max_files: self.log_max_files,
+        })
+    }
+
     pub fn get_config_path(&self) -> PathBuf {
         if let Some(path) = &self.config {
             return path.clone();
@@ -75,7 +568,7 @@ impl Cli {
         // Fall back to XDG config location
         if let Some(proj_dirs) = ProjectDirs::from("", "", "database_exporter") {
             let config_dir = proj_dirs.config_dir();
-            println!("{:#?}", config_dir);
+            log::debug!("Using the config directory {:?}", config_dir);
             std::fs::create_dir_all(config_dir).expect("Failed to create config directory");
             return config_dir.join("config.toml");
         }
@@ -83,16 +576,57 @@ impl Cli {
         panic!("Could not determine config file location");
     }
 
-    pub fn get_export_directory(&self) -> PathBuf {
+    /// Creates the export directory (and the DuckDB file's parent directory)
+    ///
+    /// Returns an error rather than panicking if any part of either path
+    /// already exists as a regular file. With `--dry-run` the paths are only
+    /// checked, nothing is created.
+    pub fn get_export_directory(&self) -> Result<PathBuf, String> {
         let path = PathBuf::from(self.export_directory.clone());
 
-        std::fs::create_dir_all(&path)
-            .unwrap_or_else(|e| panic!("Unable to create directory: {:?}\n{e}", &path));
+        if let Some(file) = find_file_ancestor(&path) {
+            return Err(format!(
+                "Export directory path exists and is a file: {}",
+                file.display()
+            ));
+        }
+
+        let create_dir_all = |path: &Path| match self.export.dry_run {
+            true => Ok(()),
+            false => std::fs::create_dir_all(path)
+                .map_err(|e| format!("Unable to create directory: {}\n{e}", path.display())),
+        };
+        create_dir_all(&path)?;
 
-        path
+        // The DuckDB file lives underneath the export directory, possibly nested
+        if self.database.include_duckdb {
+            let duckdb_path = path.join(&self.database.duckdb_file_name);
+            if duckdb_path.is_dir() {
+                return Err(format!(
+                    "DuckDB file path exists and is a directory: {}",
+                    duckdb_path.display()
+                ));
+            }
+            if let Some(parent) = duckdb_path.parent() {
+                if let Some(file) = find_file_ancestor(parent) {
+                    return Err(format!(
+                        "DuckDB file parent directory exists and is a file: {}",
+                        file.display()
+                    ));
+                }
+                create_dir_all(parent)?;
+            }
+        }
+
+        Ok(path)
     }
 }
 
+/// Returns the first component of `path` (including `path` itself) that exists as a file
+fn find_file_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.is_file())
+}
+
 /*
 
 #[derive(Clone, Debug)]
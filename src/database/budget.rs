@@ -0,0 +1,121 @@
+use std::sync::{Condvar, Mutex};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// The most tables of `bytes` each that ran at once, out of 8 running on 8 threads
+    fn peak_concurrency(budget: &MemoryBudget, bytes: u64) -> usize {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = budget.acquire(bytes);
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(10));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        peak.into_inner()
+    }
+
+    #[test]
+    fn test_memory_budget_limits_concurrency() {
+        let budget = MemoryBudget::new(100);
+
+        // Tables larger than the budget run one at a time rather than never
+        assert_eq!(peak_concurrency(&budget, 500), 1);
+        // At most three tables of 30 bytes fit
+        let peak = peak_concurrency(&budget, 30);
+        assert!((1..=3).contains(&peak), "{peak} tables ran at once");
+        // Every permit was returned
+        assert_eq!(*budget.available.lock().unwrap(), budget.capacity());
+
+        assert_eq!(MemoryBudget::from_megabytes(2).capacity(), 2 * 1024 * 1024);
+        assert_eq!(estimate_table_bytes(1_000, 4), 64_000);
+        assert_eq!(estimate_table_bytes(u64::MAX, 2), u64::MAX);
+    }
+}
+
+/// The assumed in-memory size of a single value when estimating a table's memory.
+/// The real size depends on the column types, strings in particular, so this is rough.
+const BYTES_PER_VALUE: u64 = 16;
+
+/// Estimates the memory needed to hold a table, from its row and column counts
+pub fn estimate_table_bytes(rows: u64, columns: u64) -> u64 {
+    rows.saturating_mul(columns).saturating_mul(BYTES_PER_VALUE)
+}
+
+/// A counting semaphore over bytes, which bounds the estimated memory of the
+/// tables being exported at once
+#[derive(Debug)]
+pub struct MemoryBudget {
+    capacity: u64,
+    available: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            capacity,
+            available: Mutex::new(capacity),
+            released: Condvar::new(),
+        }
+    }
+
+    /// A budget of `megabytes` MiB, see `--memory-budget-mb`
+    pub fn from_megabytes(megabytes: u64) -> Self {
+        Self::new(megabytes.saturating_mul(1024 * 1024))
+    }
+
+    /// The whole budget in bytes
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Blocks until `bytes` of the budget are free, then holds them until the
+    /// permit is dropped.
+    ///
+    /// A request larger than the whole budget is capped at it, so the table
+    /// waits for every other table to finish and then runs alone.
+    pub fn acquire(&self, bytes: u64) -> MemoryPermit<'_> {
+        let bytes = bytes.min(self.capacity);
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available < bytes {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= bytes;
+        MemoryPermit {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+/// Part of a `MemoryBudget`, returned to it when dropped
+#[derive(Debug)]
+pub struct MemoryPermit<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl Drop for MemoryPermit<'_> {
+    fn drop(&mut self) {
+        let mut available = self
+            .budget
+            .available
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *available += self.bytes;
+        self.budget.released.notify_all();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::config::SQLEngineConfig;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> TableFilter {
+        regex_filter(include, exclude, None, None)
+    }
+
+    fn regex_filter(
+        include: &[&str],
+        exclude: &[&str],
+        include_regex: Option<&str>,
+        exclude_regex: Option<&str>,
+    ) -> TableFilter {
+        let to_vec = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        let to_regex = |pattern: Option<&str>| build_regex(pattern.map(str::to_string).as_ref());
+        TableFilter {
+            include: build_glob_set(&to_vec(include)).unwrap(),
+            exclude: build_glob_set(&to_vec(exclude)).unwrap(),
+            include_regex: to_regex(include_regex).unwrap(),
+            exclude_regex: to_regex(exclude_regex).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        assert!(filter(&[], &[]).matches("anything"));
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        let f = filter(&["sales_*", "dim_?", "customer"], &[]);
+        assert!(f.matches("sales_2024"));
+        assert!(f.matches("dim_a"));
+        assert!(f.matches("customer"));
+        assert!(!f.matches("dim_ab"));
+        assert!(!f.matches("customers"));
+        assert!(!f.matches("orders"));
+    }
+
+    #[test]
+    fn test_exclude_wins_over_include() {
+        let f = filter(&["sales_*"], &["*_archive", "sales_tmp"]);
+        assert!(f.matches("sales_2024"));
+        assert!(!f.matches("sales_2019_archive"));
+        assert!(!f.matches("sales_tmp"));
+
+        let f = filter(&[], &["audit_*"]);
+        assert!(f.matches("orders"));
+        assert!(!f.matches("audit_log"));
+    }
+
+    #[test]
+    fn test_regex_patterns() {
+        let f = regex_filter(&[], &[], Some(r"^fact_\w+_\d{4}$"), Some("(?i)temp"));
+        assert!(f.matches("fact_sales_2024"));
+        assert!(!f.matches("fact_sales"));
+        assert!(!f.matches("fact_TEMP_2024"));
+        assert!(!f.matches("dim_customer"));
+    }
+
+    #[test]
+    fn test_glob_and_regex_precedence() {
+        // Includes are combined, a match on either is enough
+        let f = regex_filter(&["dim_*"], &[], Some("^fact_"), None);
+        assert!(f.matches("dim_customer"));
+        assert!(f.matches("fact_sales"));
+        assert!(!f.matches("staging"));
+
+        // Either kind of exclude wins over either kind of include
+        let f = regex_filter(&["dim_*"], &["*_old"], Some("^fact_"), Some("_bak$"));
+        assert!(!f.matches("dim_customer_old"));
+        assert!(!f.matches("dim_customer_bak"));
+        assert!(!f.matches("fact_sales_old"));
+        assert!(!f.matches("fact_sales_bak"));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_an_error() {
+        assert!(build_regex(Some(&"fact_(".to_string())).is_err());
+    }
+}
+
+/// Selects which tables of a database are exported, from the config's
+/// `include_tables`/`exclude_tables` glob patterns and
+/// `include_tables_regex`/`exclude_tables_regex` regular expressions.
+///
+/// A table is included if it matches any include glob or the include regex,
+/// when neither is configured every table is included. A table matching an
+/// exclude glob or the exclude regex is always skipped, excludes win over includes.
+#[derive(Debug)]
+pub struct TableFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    include_regex: Option<Regex>,
+    exclude_regex: Option<Regex>,
+}
+
+impl TableFilter {
+    /// Compiles the table patterns of a database's config
+    ///
+    /// # Returns
+    ///
+    /// An error describing the first invalid pattern
+    pub fn new(config: &SQLEngineConfig) -> Result<Self, String> {
+        Ok(Self {
+            include: build_glob_set(config.include_tables.as_deref().unwrap_or_default())?,
+            exclude: build_glob_set(config.exclude_tables.as_deref().unwrap_or_default())?,
+            include_regex: build_regex(config.include_tables_regex.as_ref())?,
+            exclude_regex: build_regex(config.exclude_tables_regex.as_ref())?,
+        })
+    }
+
+    /// Whether a table should be exported
+    pub fn matches(&self, table: &str) -> bool {
+        let excluded = self.exclude.as_ref().is_some_and(|set| set.is_match(table))
+            || self
+                .exclude_regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(table));
+        if excluded {
+            return false;
+        }
+        if self.include.is_none() && self.include_regex.is_none() {
+            return true;
+        }
+        self.include.as_ref().is_some_and(|set| set.is_match(table))
+            || self
+                .include_regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(table))
+    }
+}
+
+/// Compiles an optional regular expression
+fn build_regex(pattern: Option<&String>) -> Result<Option<Regex>, String> {
+    pattern
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| format!("Invalid table regex '{pattern}': {e}"))
+        })
+        .transpose()
+}
+
+/// Compiles a list of glob patterns, `None` if the list is empty
+fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).map_err(|e| format!("Invalid table pattern '{pattern}': {e}"))?;
+        builder.add(glob);
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
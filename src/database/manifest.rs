@@ -0,0 +1,529 @@
+use super::{DatabaseError, ExportStatus, TableOutcome};
+use crate::helpers::display_path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ExportOptions;
+    use crate::config::CustomQuery;
+    use crate::database::run_table_export;
+    use crate::helpers::TableParquet;
+    use crate::test_helpers::{temp_dir, SqliteFixture};
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_manifest_of_an_export() {
+        let fixture = SqliteFixture::new(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT);
+             INSERT INTO notes (body) VALUES ('first'), ('second');",
+        );
+        let export_directory = fixture.dir().join("export");
+        let outcomes = fixture
+            .database("")
+            .export_dataframes(
+                None,
+                &export_directory,
+                None,
+                "joplin",
+                None,
+                Some(vec![CustomQuery::new(
+                    "broken",
+                    "Reads a missing table",
+                    "SELECT * FROM missing",
+                )]),
+                &ExportOptions::parse_from(["export"]),
+            )
+            .unwrap();
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin", &outcomes);
+        let path = export_directory.join("manifest.json");
+        manifest.write(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let tables = json["tables"].as_array().unwrap();
+        let notes = export_directory.join("joplin").join("notes.parquet");
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0]["database"], "Joplin");
+        assert_eq!(tables[0]["table"], "notes");
+        assert_eq!(tables[0]["file_path"], notes.display().to_string());
+        assert_eq!(tables[0]["status"], "success");
+        assert_eq!(tables[0]["rows"], 2);
+        assert_eq!(tables[0]["bytes"], std::fs::metadata(&notes).unwrap().len());
+        assert_eq!(tables[0]["sha256"], sha256_file(&notes).unwrap());
+        assert_eq!(tables[1]["table"], "broken");
+        assert_eq!(tables[1]["status"], "failed");
+        assert!(tables[1]["error"].as_str().unwrap().contains("missing"));
+        assert!(tables[1]["rows"].is_null());
+    }
+
+    #[test]
+    fn test_unreadable_manifest_is_a_json_error() {
+        let dir = temp_dir();
+        let path = dir.path().join("manifest.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            verify_manifest(&path),
+            Err(DatabaseError::JsonError(..))
+        ));
+    }
+
+    #[test]
+    fn test_skipped_tables_lists_panics() {
+        let dir = temp_dir();
+        let table = |name: &str| TableParquet {
+            file_path: dir.path().join(format!("{name}.parquet")),
+            table_name: name.to_string(),
+        };
+
+        let outcomes = [
+            run_table_export(&table("notes"), || Ok(3)),
+            run_table_export(&table("tags"), || panic!("unsupported type: XML")),
+            run_table_export(&table("users"), || {
+                Err(DatabaseError::ValidationError("bad column".to_string()))
+            }),
+        ];
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin", &outcomes);
+        let path = dir.path().join("skipped_tables.json");
+        manifest.write_skipped(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let skipped = json.as_array().unwrap();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0]["database"], "Joplin");
+        assert_eq!(skipped[0]["table"], "tags");
+        assert_eq!(skipped[0]["status"], "panicked");
+        assert_eq!(skipped[0]["error"], "unsupported type: XML");
+        assert_eq!(skipped[1]["table"], "users");
+        assert_eq!(skipped[1]["status"], "failed");
+        assert_eq!(skipped[1]["error"], "Validation Error: bad column");
+    }
+
+    #[test]
+    fn test_verify_manifest_checksums() {
+        let dir = temp_dir();
+        let schema_dir = dir.path().join("joplin");
+        std::fs::create_dir_all(&schema_dir).unwrap();
+        let table = |name: &str| {
+            let file_path = schema_dir.join(format!("{name}.parquet"));
+            std::fs::write(&file_path, name.repeat(1000)).unwrap();
+            TableParquet {
+                file_path,
+                table_name: name.to_string(),
+            }
+        };
+        let outcomes = [
+            run_table_export(&table("notes"), || Ok(3)),
+            run_table_export(&table("tags"), || Ok(2)),
+            run_table_export(&table("folders"), || Ok(1)),
+        ];
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin", &outcomes);
+        let path = dir.path().join("manifest.json");
+        manifest.write(&path).unwrap();
+
+        let clean = verify_manifest(&path).unwrap();
+
+        // Corrupt one file and lose another
+        std::fs::write(schema_dir.join("tags.parquet"), "tampered").unwrap();
+        std::fs::remove_file(schema_dir.join("folders.parquet")).unwrap();
+        let tampered = verify_manifest(&path).unwrap();
+
+        assert_eq!(
+            manifest.tables[0].sha256.as_deref(),
+            Some(format!("{:x}", Sha256::digest("notes".repeat(1000))).as_str())
+        );
+        assert_eq!(clean.len(), 3);
+        assert!(clean.iter().all(|r| r.problem.is_none()));
+
+        assert!(tampered[0].problem.is_none());
+        assert!(matches!(
+            tampered[1].problem,
+            Some(ChecksumProblem::Mismatch { .. })
+        ));
+        assert_eq!(tampered[2].problem, Some(ChecksumProblem::Missing));
+    }
+
+    #[test]
+    fn test_manifest_records_successes_and_failures() {
+        let dir = temp_dir();
+        let notes = dir.path().join("notes.parquet");
+        std::fs::write(&notes, [0u8; 42]).unwrap();
+
+        let outcomes = [
+            TableOutcome::new(
+                &TableParquet {
+                    file_path: notes,
+                    table_name: "notes".to_string(),
+                },
+                ExportStatus::Success { rows: 3 },
+                Duration::from_millis(1500),
+            ),
+            TableOutcome::new(
+                &TableParquet {
+                    file_path: PathBuf::from("tags.parquet"),
+                    table_name: "tags".to_string(),
+                },
+                ExportStatus::Panicked("unsupported type".to_string()),
+                Duration::ZERO,
+            ),
+        ];
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin", &outcomes);
+        let path = dir.path().join("manifest.json");
+        manifest.write(&path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let tables = json["tables"].as_array().unwrap();
+        assert_eq!(json["run_id"], "run");
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0]["database"], "Joplin");
+        assert_eq!(tables[0]["status"], "success");
+        assert_eq!(tables[0]["rows"], 3);
+        assert_eq!(tables[0]["bytes"], 42);
+        assert_eq!(tables[1]["status"], "panicked");
+        assert_eq!(tables[1]["error"], "unsupported type");
+        assert!(tables[1]["rows"].is_null());
+    }
+
+    #[test]
+    fn test_manifest_records_timing() {
+        let outcome = |name: &str, status: ExportStatus, millis: u64| {
+            let tp = TableParquet {
+                file_path: PathBuf::from(format!("{name}.parquet")),
+                table_name: name.to_string(),
+            };
+            TableOutcome::new(&tp, status, Duration::from_millis(millis))
+        };
+        let outcomes = [
+            outcome("notes", ExportStatus::Success { rows: 3000 }, 1500),
+            outcome("tags", ExportStatus::Success { rows: 20 }, 4000),
+            outcome("users", ExportStatus::Failed("bad".to_string()), 200),
+            outcome("empty", ExportStatus::Success { rows: 0 }, 0),
+        ];
+        // Exports that ran are timed
+        let timed = run_table_export(&outcomes[0].table_parquet(), || {
+            std::thread::sleep(Duration::from_millis(1));
+            Ok(1)
+        });
+        assert!(timed.duration > Duration::ZERO);
+        assert!(timed.rows_per_sec().is_some());
+
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin", &outcomes);
+        let summary = manifest.record_timing(Duration::from_secs(5));
+        assert_eq!(
+            summary.to_string(),
+            "3020 rows in 5.0s, slowest: Joplin.tags (4.0s), Joplin.notes (1.5s), \
+             Joplin.users (0.2s), Joplin.empty (0.0s)"
+        );
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        let tables = json["tables"].as_array().unwrap();
+        assert_eq!(tables[0]["duration_secs"], 1.5);
+        assert_eq!(tables[0]["rows_per_sec"], 2000.0);
+        assert_eq!(tables[1]["rows_per_sec"], 5.0);
+        // Nothing to divide by for failures and instant exports
+        assert!(tables[2]["rows_per_sec"].is_null());
+        assert!(tables[3]["rows_per_sec"].is_null());
+        assert_eq!(json["timing"]["total_rows"], 3020);
+        assert_eq!(json["timing"]["total_secs"], 5.0);
+        assert_eq!(json["timing"]["slowest"][0]["table"], "tags");
+        assert_eq!(json["timing"]["slowest"][0]["rows"], 20);
+    }
+}
+
+/// The number of tables listed in the timing summary's `slowest`
+const SLOWEST_TABLES: usize = 5;
+
+/// A single table or custom query in the manifest
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    /// The name of the database in the config, i.e. the schema the table is exported to
+    pub database: String,
+    pub table: String,
+    pub file_path: String,
+    /// `success`, `failed` or `panicked`
+    pub status: &'static str,
+    /// The error or panic message if the export did not succeed
+    pub error: Option<String>,
+    pub rows: Option<usize>,
+    /// The size of the file, or of all files for partitioned tables
+    pub bytes: Option<u64>,
+    /// The hex SHA-256 of the file, partitioned tables aren't checksummed
+    pub sha256: Option<String>,
+    pub duration_secs: f64,
+    /// The rows written per second, if the export succeeded
+    pub rows_per_sec: Option<f64>,
+    /// Why the table is missing from the DuckDB database, if it couldn't be loaded
+    pub duckdb_error: Option<String>,
+}
+
+/// A table or custom query that failed or panicked, written to `skipped_tables.json`
+#[derive(Debug, Serialize)]
+pub struct SkippedTable<'a> {
+    pub database: &'a str,
+    pub table: &'a str,
+    /// `failed` or `panicked`
+    pub status: &'static str,
+    pub error: Option<&'a str>,
+}
+
+/// A table in the timing summary
+#[derive(Debug, Serialize)]
+pub struct SlowTable {
+    pub database: String,
+    pub table: String,
+    pub duration_secs: f64,
+    pub rows: Option<usize>,
+}
+
+/// How long a run took and which tables took the longest
+#[derive(Debug, Serialize)]
+pub struct TimingSummary {
+    /// The rows written across every table and custom query
+    pub total_rows: usize,
+    /// The wall-clock time of the run, tables exported in parallel overlap
+    pub total_secs: f64,
+    /// The slowest tables, slowest first
+    pub slowest: Vec<SlowTable>,
+}
+
+impl std::fmt::Display for TimingSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} rows in {:.1}s", self.total_rows, self.total_secs)?;
+        if !self.slowest.is_empty() {
+            let slowest: Vec<String> = self
+                .slowest
+                .iter()
+                .map(|t| format!("{}.{} ({:.1}s)", t.database, t.table, t.duration_secs))
+                .collect();
+            write!(f, ", slowest: {}", slowest.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A machine-readable summary of a run, written to `manifest.json` in the export directory
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub run_id: String,
+    /// When the run started, in RFC 3339 format
+    pub exported_at: String,
+    pub tables: Vec<ManifestEntry>,
+    /// Set by `record_timing` once every database has been exported
+    pub timing: Option<TimingSummary>,
+}
+
+impl Manifest {
+    /// Creates an empty manifest stamped with the current time
+    pub fn new(run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            tables: Vec::new(),
+            timing: None,
+        }
+    }
+
+    /// Summarises the rows and time of the tables added so far
+    ///
+    /// * `total` - The wall-clock time of the run
+    pub fn record_timing(&mut self, total: Duration) -> &TimingSummary {
+        let mut by_duration: Vec<&ManifestEntry> = self.tables.iter().collect();
+        by_duration.sort_by(|a, b| b.duration_secs.total_cmp(&a.duration_secs));
+        let summary = TimingSummary {
+            total_rows: self.tables.iter().filter_map(|t| t.rows).sum(),
+            total_secs: total.as_secs_f64(),
+            slowest: by_duration
+                .into_iter()
+                .take(SLOWEST_TABLES)
+                .map(|t| SlowTable {
+                    database: t.database.clone(),
+                    table: t.table.clone(),
+                    duration_secs: t.duration_secs,
+                    rows: t.rows,
+                })
+                .collect(),
+        };
+        self.timing.insert(summary)
+    }
+
+    /// Adds an entry for each table exported from a database
+    pub fn add_outcomes(&mut self, database: &str, outcomes: &[TableOutcome]) {
+        self.tables.extend(outcomes.iter().map(|outcome| {
+            ManifestEntry {
+                database: database.to_string(),
+                table: outcome.table_name.clone(),
+                file_path: outcome.file_path.display().to_string(),
+                status: outcome.status.label(),
+                error: match &outcome.status {
+                    ExportStatus::Success { .. } => None,
+                    ExportStatus::Failed(e) | ExportStatus::Panicked(e) => Some(e.clone()),
+                },
+                rows: outcome.rows(),
+                bytes: outcome
+                    .succeeded()
+                    .then(|| size_on_disk(&outcome.file_path)),
+                sha256: outcome
+                    .succeeded()
+                    .then(|| sha256_file(&outcome.file_path).ok())
+                    .flatten(),
+                duration_secs: outcome.duration.as_secs_f64(),
+                rows_per_sec: outcome.rows_per_sec(),
+                duckdb_error: outcome.duckdb_error.clone(),
+            }
+        }));
+    }
+
+    /// Writes the manifest as pretty printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), DatabaseError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            DatabaseError::JsonError("Unable to serialize the manifest".to_string(), e)
+        })?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Returns the tables that failed or panicked
+    pub fn skipped(&self) -> Vec<SkippedTable<'_>> {
+        self.tables
+            .iter()
+            .filter(|entry| entry.error.is_some())
+            .map(|entry| SkippedTable {
+                database: &entry.database,
+                table: &entry.table,
+                status: entry.status,
+                error: entry.error.as_deref(),
+            })
+            .collect()
+    }
+
+    /// Writes the tables that failed or panicked as pretty printed JSON.
+    ///
+    /// The file is written even when nothing was skipped, so a list from a
+    /// previous run is never mistaken for the current one.
+    pub fn write_skipped(&self, path: &Path) -> Result<(), DatabaseError> {
+        let json = serde_json::to_string_pretty(&self.skipped()).map_err(|e| {
+            DatabaseError::JsonError("Unable to serialize the skipped tables".to_string(), e)
+        })?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// The checksum of a file recorded in a manifest, as read back by `verify_manifest`
+#[derive(Debug, Deserialize)]
+struct ChecksumEntry {
+    database: String,
+    table: String,
+    file_path: PathBuf,
+    sha256: Option<String>,
+}
+
+/// The parts of `manifest.json` needed to verify it
+#[derive(Debug, Deserialize)]
+struct ManifestChecksums {
+    tables: Vec<ChecksumEntry>,
+}
+
+/// A file that no longer matches its manifest
+#[derive(Debug, PartialEq)]
+pub enum ChecksumProblem {
+    Missing,
+    Mismatch { expected: String, actual: String },
+}
+
+/// The result of checking a single file against the manifest
+#[derive(Debug)]
+pub struct ChecksumResult {
+    pub database: String,
+    pub table: String,
+    pub file_path: PathBuf,
+    /// `None` if the checksum matched
+    pub problem: Option<ChecksumProblem>,
+}
+
+/// Recomputes the checksum of every file in a manifest and compares it with the recorded one.
+///
+/// Files are looked for at their recorded path and, failing that, in their
+/// schema directory next to the manifest, so an export directory that has been
+/// moved or copied elsewhere can still be verified.
+///
+/// # Returns
+///
+/// A result for every file with a checksum, i.e. every successful, unpartitioned table
+pub fn verify_manifest(path: &Path) -> Result<Vec<ChecksumResult>, DatabaseError> {
+    let manifest: ManifestChecksums = serde_json::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| {
+            DatabaseError::JsonError(format!("Unable to read {}", display_path(path)), e)
+        })?;
+    let manifest_dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut results = Vec::new();
+    for entry in manifest.tables {
+        let Some(expected) = entry.sha256 else {
+            continue;
+        };
+        let problem = match locate(&entry.file_path, manifest_dir) {
+            None => Some(ChecksumProblem::Missing),
+            Some(file) => {
+                let actual = sha256_file(&file)?;
+                (actual != expected).then_some(ChecksumProblem::Mismatch { expected, actual })
+            }
+        };
+        results.push(ChecksumResult {
+            database: entry.database,
+            table: entry.table,
+            file_path: entry.file_path,
+            problem,
+        });
+    }
+    Ok(results)
+}
+
+/// Finds a file from the manifest, at its recorded path or as `<schema>/<file>` in `manifest_dir`
+fn locate(file_path: &Path, manifest_dir: &Path) -> Option<PathBuf> {
+    if file_path.is_file() {
+        return Some(file_path.to_path_buf());
+    }
+    let file_name = file_path.file_name()?;
+    let schema = file_path.parent()?.file_name()?;
+    let moved = manifest_dir.join(schema).join(file_name);
+    moved.is_file().then_some(moved)
+}
+
+/// Returns the hex SHA-256 of a file, reading it in chunks rather than all at once
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the size of a file, or the total size of the files under a directory
+fn size_on_disk(path: &Path) -> u64 {
+    if path.is_dir() {
+        std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| size_on_disk(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0)
+    } else {
+        std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
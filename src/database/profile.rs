@@ -0,0 +1,79 @@
+use super::DatabaseError;
+use polars::frame::DataFrame;
+use polars::prelude::{AnyValue, Series};
+use serde::Serialize;
+use std::path::Path;
+
+/// Summary statistics for a single column of an exported table
+#[derive(Debug, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub dtype: String,
+    pub null_count: usize,
+    /// `None` if the column type can't be hashed
+    pub distinct_count: Option<usize>,
+    /// `None` if the column is empty, entirely null or can't be ordered
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+impl ColumnProfile {
+    /// Computes the statistics of a column
+    pub fn new(series: &Series) -> Self {
+        Self {
+            name: series.name().to_string(),
+            dtype: series.dtype().to_string(),
+            null_count: series.null_count(),
+            distinct_count: series.n_unique().ok(),
+            min: series
+                .min_reduce()
+                .ok()
+                .and_then(|s| any_value_to_string(s.value())),
+            max: series
+                .max_reduce()
+                .ok()
+                .and_then(|s| any_value_to_string(s.value())),
+        }
+    }
+}
+
+/// Summary statistics for an exported table
+#[derive(Debug, Serialize)]
+pub struct TableProfile {
+    pub rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+impl TableProfile {
+    /// Computes the statistics of every column in a DataFrame
+    pub fn new(df: &DataFrame) -> Self {
+        Self {
+            rows: df.height(),
+            columns: df
+                .get_columns()
+                .iter()
+                .map(|c| ColumnProfile::new(c.as_materialized_series()))
+                .collect(),
+        }
+    }
+
+    /// Writes the profile as pretty printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), DatabaseError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DatabaseError::JsonError("Unable to serialize profile".to_string(), e))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Renders a value without the quotes polars adds to strings, nulls become `None`
+fn any_value_to_string(value: &AnyValue) -> Option<String> {
+    match value {
+        AnyValue::Null => None,
+        v => Some(
+            v.get_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| v.to_string()),
+        ),
+    }
+}
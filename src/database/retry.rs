@@ -0,0 +1,157 @@
+use super::DatabaseError;
+use crate::cli::ExportOptions;
+use log::warn;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_retries_transient_errors() {
+        // Fails twice with a dropped connection, then succeeds
+        let mut attempts = 0;
+        let result = policy(3).run("mock export", || {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(DatabaseError::IoError(ErrorKind::ConnectionReset.into())),
+                _ => Ok(42),
+            }
+        });
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts, 3);
+
+        // Gives up after max_retries
+        let mut attempts = 0;
+        let result: Result<(), _> = policy(1).run("mock export", || {
+            attempts += 1;
+            Err(DatabaseError::IoError(ErrorKind::TimedOut.into()))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn test_does_not_retry_other_errors() {
+        let mut attempts = 0;
+        let result: Result<(), _> = policy(3).run("mock export", || {
+            attempts += 1;
+            Err(DatabaseError::ValidationError("wrong type".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+    }
+}
+
+/// Fragments of error messages from the source drivers that indicate a dropped
+/// or unreachable connection, compared in lowercase
+const TRANSIENT_MESSAGES: [&str; 9] = [
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "connection aborted",
+    "broken pipe",
+    "timed out",
+    "timeout",
+    "network",
+    "unexpected eof",
+];
+
+/// How often and how quickly failed table exports are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The number of attempts after the first, zero disables retrying
+    pub max_retries: u32,
+    /// The delay before the first retry, doubled for each retry after it
+    pub base_delay: Duration,
+}
+
+impl From<&ExportOptions> for RetryPolicy {
+    fn from(opts: &ExportOptions) -> Self {
+        Self {
+            max_retries: opts.max_retries,
+            base_delay: Duration::from_millis(opts.retry_base_delay_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before retry number `retry` (starting at 0)
+    fn delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+
+    /// Runs `op`, retrying it with exponential backoff while it fails with a transient error.
+    ///
+    /// Errors that aren't transient, such as type or schema errors, are returned
+    /// immediately. Retrying stops early if a shutdown is requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` - What is being attempted, used in the log messages
+    /// * `op` - The operation to run
+    pub fn run<T>(
+        &self,
+        description: &str,
+        mut op: impl FnMut() -> Result<T, DatabaseError>,
+    ) -> Result<T, DatabaseError> {
+        let mut retry = 0;
+        loop {
+            match op() {
+                Err(e) if retry < self.max_retries && is_transient(&e) => {
+                    if crate::shutdown::requested() {
+                        return Err(e);
+                    }
+                    let delay = self.delay(retry);
+                    retry += 1;
+                    warn!(
+                        "{description} failed, retrying in {delay:?} (attempt {retry} of {})\n{e}",
+                        self.max_retries
+                    );
+                    crate::shutdown::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether an error looks like a network or connection problem that may succeed if retried
+fn is_transient(error: &DatabaseError) -> bool {
+    match error {
+        DatabaseError::IoError(e) => matches!(
+            e.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected
+                | ErrorKind::BrokenPipe
+                | ErrorKind::TimedOut
+                | ErrorKind::Interrupted
+                | ErrorKind::UnexpectedEof
+        ),
+        DatabaseError::ArrowError(e) => {
+            let message = e.to_string().to_lowercase();
+            TRANSIENT_MESSAGES.iter().any(|m| message.contains(m))
+        }
+        _ => false,
+    }
+}
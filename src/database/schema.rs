@@ -0,0 +1,202 @@
+use super::DatabaseError;
+use crate::cli::SqlDialect;
+use polars::frame::DataFrame;
+use polars::prelude::DataType;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::TimeUnit;
+
+    #[test]
+    fn test_create_table_statement() {
+        let columns = [
+            ("id".to_string(), DataType::Int64),
+            ("price".to_string(), DataType::Float64),
+            ("name".to_string(), DataType::String),
+            (
+                "created_at".to_string(),
+                DataType::Datetime(TimeUnit::Microseconds, None),
+            ),
+        ];
+
+        assert_eq!(
+            create_table_statement(SqlDialect::Postgres, "shop", "orders", &columns),
+            "CREATE TABLE IF NOT EXISTS \"shop\".\"orders\" (
+    \"id\" BIGINT,
+    \"price\" DOUBLE PRECISION,
+    \"name\" TEXT,
+    \"created_at\" TIMESTAMP
+);
+"
+        );
+        assert_eq!(
+            create_table_statement(SqlDialect::Duckdb, "shop", "orders", &columns),
+            "CREATE TABLE IF NOT EXISTS \"shop\".\"orders\" (
+    \"id\" BIGINT,
+    \"price\" DOUBLE,
+    \"name\" VARCHAR,
+    \"created_at\" TIMESTAMP
+);
+"
+        );
+    }
+}
+
+/// The name and type of a single column of an exported table
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub dtype: String,
+    /// Whether the exported column contains any nulls, the source's `NOT NULL`
+    /// constraints aren't carried through ConnectorX
+    pub nullable: bool,
+}
+
+/// The columns of an exported table, in file order
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct TableSchema {
+    pub columns: Vec<ColumnSchema>,
+}
+
+impl TableSchema {
+    /// The schema of a DataFrame
+    pub fn new(df: &DataFrame) -> Self {
+        let mut schema = Self::default();
+        schema.extend(df);
+        schema
+    }
+
+    /// Adds the columns of another batch of the same table, a column is
+    /// nullable if it contains nulls in any batch
+    pub fn extend(&mut self, df: &DataFrame) {
+        for column in df.get_columns() {
+            let nullable = column.null_count() > 0;
+            match self
+                .columns
+                .iter_mut()
+                .find(|c| c.name == column.name().as_str())
+            {
+                Some(existing) => existing.nullable |= nullable,
+                None => self.push(column.name(), column.dtype(), nullable),
+            }
+        }
+    }
+
+    /// Adds a column that isn't in the DataFrame, e.g. one added as the file is written
+    pub fn push(&mut self, name: &str, dtype: &DataType, nullable: bool) {
+        self.columns.push(ColumnSchema {
+            name: name.to_string(),
+            dtype: dtype.to_string(),
+            nullable,
+        });
+    }
+
+    /// Whether the schema has a column named `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.columns.iter().any(|c| c.name == name)
+    }
+
+    /// Writes the schema as pretty printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), DatabaseError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DatabaseError::JsonError("Unable to serialize schema".to_string(), e))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// The name and type of each column of a DataFrame, in order
+pub fn column_types(df: &DataFrame) -> Vec<(String, DataType)> {
+    df.get_columns()
+        .iter()
+        .map(|c| (c.name().to_string(), c.dtype().clone()))
+        .collect()
+}
+
+/// Builds the `CREATE TABLE` statement for an exported table, in `schema`.
+///
+/// Columns are left nullable, whether a column contains nulls says little about
+/// whether it may.
+///
+/// # Arguments
+///
+/// * `dialect` - The SQL dialect of the target database
+/// * `schema` - The schema the table is created in, e.g. the database's name
+/// * `table` - The exported name of the table
+/// * `columns` - The name and type of each column, see `column_types`
+pub fn create_table_statement(
+    dialect: SqlDialect,
+    schema: &str,
+    table: &str,
+    columns: &[(String, DataType)],
+) -> String {
+    let columns = columns
+        .iter()
+        .map(|(name, dtype)| {
+            format!(
+                "    {} {}",
+                quote_identifier(name),
+                sql_type(dtype, dialect)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!(
+        "CREATE TABLE IF NOT EXISTS {}.{} (\n{columns}\n);\n",
+        quote_identifier(schema),
+        quote_identifier(table)
+    )
+}
+
+/// The `CREATE SCHEMA` statement for the schema the tables of a database are created in
+pub fn create_schema_statement(schema: &str) -> String {
+    format!(
+        "CREATE SCHEMA IF NOT EXISTS {};\n",
+        quote_identifier(schema)
+    )
+}
+
+/// Quotes an identifier with `"`, which both Postgres and DuckDB accept
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The SQL type a column of a polars type is created as.
+///
+/// Postgres has no unsigned integers, so they're widened to the next signed type
+/// that holds every value. Types without an equivalent are created as text.
+fn sql_type(dtype: &DataType, dialect: SqlDialect) -> String {
+    let postgres = dialect == SqlDialect::Postgres;
+    match dtype {
+        DataType::Boolean => "BOOLEAN".to_string(),
+        DataType::Int8 if postgres => "SMALLINT".to_string(),
+        DataType::Int8 => "TINYINT".to_string(),
+        DataType::Int16 => "SMALLINT".to_string(),
+        DataType::Int32 => "INTEGER".to_string(),
+        DataType::Int64 => "BIGINT".to_string(),
+        DataType::UInt8 if postgres => "SMALLINT".to_string(),
+        DataType::UInt8 => "UTINYINT".to_string(),
+        DataType::UInt16 if postgres => "INTEGER".to_string(),
+        DataType::UInt16 => "USMALLINT".to_string(),
+        DataType::UInt32 if postgres => "BIGINT".to_string(),
+        DataType::UInt32 => "UINTEGER".to_string(),
+        DataType::UInt64 if postgres => "NUMERIC(20, 0)".to_string(),
+        DataType::UInt64 => "UBIGINT".to_string(),
+        DataType::Float32 => "REAL".to_string(),
+        DataType::Float64 if postgres => "DOUBLE PRECISION".to_string(),
+        DataType::Float64 => "DOUBLE".to_string(),
+        DataType::Binary if postgres => "BYTEA".to_string(),
+        DataType::Binary => "BLOB".to_string(),
+        DataType::Date => "DATE".to_string(),
+        DataType::Time => "TIME".to_string(),
+        DataType::Datetime(_, None) => "TIMESTAMP".to_string(),
+        DataType::Datetime(_, Some(_)) => "TIMESTAMPTZ".to_string(),
+        DataType::Duration(_) => "INTERVAL".to_string(),
+        DataType::List(inner) => format!("{}[]", sql_type(inner, dialect)),
+        _ if postgres => "TEXT".to_string(),
+        _ => "VARCHAR".to_string(),
+    }
+}
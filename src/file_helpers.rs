@@ -1,9 +1,354 @@
 #[cfg(feature = "duckdb")]
+use crate::cli::DuckDBExportOptions;
+#[cfg(feature = "duckdb")]
+use crate::config::TableConfig;
+#[cfg(feature = "duckdb")]
+use crate::database::TableOutcome;
+#[cfg(feature = "duckdb")]
+use crate::helpers::display_path;
+#[cfg(feature = "duckdb")]
 use crate::helpers::TableParquet;
 #[cfg(feature = "duckdb")]
+use duckdb::arrow::ipc::reader::StreamReader;
+#[cfg(feature = "duckdb")]
+use duckdb::arrow::record_batch::RecordBatch;
+#[cfg(feature = "duckdb")]
+use duckdb::vtab::{arrow_recordbatch_to_query_params, ArrowVTab};
+#[cfg(feature = "duckdb")]
 use duckdb::Connection;
 #[cfg(feature = "duckdb")]
+use log::{error, info};
+#[cfg(feature = "duckdb")]
+use polars::prelude::{CompatLevel, DataFrame, IpcStreamWriter, SerWriter};
+#[cfg(feature = "duckdb")]
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+#[cfg(feature = "duckdb")]
 use std::path::{Path, PathBuf};
+#[cfg(feature = "duckdb")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "duckdb")]
+use std::sync::Mutex;
+
+#[cfg(all(test, feature = "duckdb"))]
+mod tests {
+    use super::*;
+    use crate::helpers::find_table_files;
+    use crate::test_helpers::{duckdb_options, temp_dir};
+
+    #[test]
+    fn test_duckdb_loader() {
+        let dir = temp_dir();
+        let database = dir.path().join("database.duckdb");
+        let loader = DuckDBLoader::open(&database, "Notes", &duckdb_options()).unwrap();
+
+        // More rows than DuckDB reads from one record batch, then a second batch
+        let mut first = polars::df!(
+            "id" => (0..5000).collect::<Vec<i64>>(),
+            "title" => (0..5000).map(|i| format!("note {i}")).collect::<Vec<_>>()
+        )
+        .unwrap();
+        let mut second = polars::df!("id" => [5000i64], "title" => [None::<&str>]).unwrap();
+        loader.load(&mut first, "notes", false).unwrap();
+        loader.load(&mut second, "notes", true).unwrap();
+        // Loading it again replaces the table
+        let mut empty = first.head(Some(0));
+        loader.load(&mut empty, "empty", false).unwrap();
+        loader.load(&mut first, "empty", false).unwrap();
+        loader.finish(&["notes", "empty"], &duckdb_options(), &HashMap::new());
+
+        let conn = Connection::open(&database).unwrap();
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM notes.notes"), 5001);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM notes.notes WHERE title IS NULL"),
+            1
+        );
+        assert_eq!(
+            count("SELECT SUM(id)::BIGINT FROM notes.notes"),
+            (0..=5000).sum::<i64>()
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM notes.empty"), 5000);
+        let title_type: String = conn
+            .query_row(
+                "SELECT data_type FROM information_schema.columns
+                 WHERE table_name = 'notes' AND column_name = 'title'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(title_type, "VARCHAR");
+    }
+
+    #[test]
+    fn test_post_load_sql() {
+        let dir = temp_dir();
+        let album = dir.path().join("album.parquet");
+        Connection::open_in_memory()
+            .unwrap()
+            .execute_batch(&format!(
+                "COPY (SELECT range AS id, range % 3 AS artist_id FROM range(10))
+                 TO '{}' (FORMAT parquet);",
+                album.display()
+            ))
+            .unwrap();
+        let sql_file = dir.path().join("post_load.sql");
+        std::fs::write(
+            &sql_file,
+            "CREATE TABLE db.top_artist AS SELECT artist_id FROM db.summary ORDER BY albums DESC LIMIT 1;",
+        )
+        .unwrap();
+        let options = DuckDBExportOptions {
+            post_load_sql: vec![
+                "CREATE TABLE db.summary AS
+                 SELECT artist_id, COUNT(*) AS albums FROM db.album GROUP BY artist_id"
+                    .to_string(),
+                "SELECT * FROM db.missing_table".to_string(),
+            ],
+            post_load_sql_file: Some(sql_file),
+            ..duckdb_options()
+        };
+
+        let database = dir.path().join("database.duckdb");
+        write_parquet_files_to_duckdb_table(
+            vec![TableParquet {
+                table_name: "album".to_string(),
+                file_path: album,
+            }],
+            "db",
+            &database,
+            &options,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let conn = Connection::open(&database).unwrap();
+        let summary_rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM db.summary", [], |row| row.get(0))
+            .unwrap();
+        let top_artist: i64 = conn
+            .query_row("SELECT artist_id FROM db.top_artist", [], |row| row.get(0))
+            .unwrap();
+        drop(conn);
+
+        assert_eq!(summary_rows, 3);
+        // The file still ran after the failing statement
+        assert_eq!(top_artist, 0);
+    }
+
+    #[test]
+    fn test_duckdb_limits() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_limits(
+            &conn,
+            &DuckDBLimits {
+                memory_limit: Some("2GiB".to_string()),
+                threads: NonZeroUsize::new(3),
+            },
+        )
+        .unwrap();
+        let (memory_limit, threads): (String, i64) = conn
+            .query_row(
+                "SELECT current_setting('memory_limit'), current_setting('threads')",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(memory_limit, "2.0 GiB");
+        assert_eq!(threads, 3);
+
+        // Invalid limits are an error rather than silently ignored
+        let invalid = DuckDBLimits {
+            memory_limit: Some("lots".to_string()),
+            threads: None,
+        };
+        assert!(apply_limits(&conn, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_duckdb_loads_every_table() {
+        let dir = temp_dir();
+        let writer = Connection::open_in_memory().unwrap();
+        let parquets: Vec<TableParquet> = (0..12)
+            .map(|i| {
+                let file_path = dir.path().join(format!("table_{i}.parquet"));
+                writer
+                    .execute_batch(&format!(
+                        "COPY (SELECT * FROM range({i})) TO '{}' (FORMAT parquet);",
+                        file_path.display()
+                    ))
+                    .unwrap();
+                TableParquet {
+                    table_name: format!("table_{i}"),
+                    file_path,
+                }
+            })
+            .collect();
+
+        let database = dir.path().join("database.duckdb");
+        let failures = write_parquet_files_to_duckdb_table(
+            parquets,
+            "db",
+            &database,
+            &duckdb_options(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        let conn = Connection::open(&database).unwrap();
+        let rows: Vec<i64> = (0..12)
+            .map(|i| {
+                conn.query_row(&format!("SELECT COUNT(*) FROM db.table_{i}"), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+            })
+            .collect();
+        drop(conn);
+
+        assert!(failures.is_empty());
+        assert_eq!(rows, (0..12).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn test_duckdb_separator_per_database() {
+        let dir = temp_dir();
+        let parquet = |name: &str, rows: i64| {
+            let file_path = dir.path().join(format!("{name}.parquet"));
+            Connection::open_in_memory()
+                .unwrap()
+                .execute_batch(&format!(
+                    "COPY (SELECT * FROM range({rows})) TO '{}' (FORMAT parquet);",
+                    file_path.display()
+                ))
+                .unwrap();
+            vec![TableParquet {
+                table_name: name.to_string(),
+                file_path,
+            }]
+        };
+
+        // Both databases share one DuckDB file, each named its own way
+        let database = dir.path().join("database.duckdb");
+        let schema_qualified = duckdb_options();
+        let joined = DuckDBExportOptions {
+            separator: Some("__".to_string()),
+            ..duckdb_options()
+        };
+        for (tables, schema, options) in [
+            (parquet("notes", 3), "joplin", &schema_qualified),
+            (parquet("items", 5), "zotero", &joined),
+        ] {
+            let failures = write_parquet_files_to_duckdb_table(
+                tables,
+                schema,
+                &database,
+                options,
+                &HashMap::new(),
+            )
+            .unwrap();
+            assert!(failures.is_empty());
+        }
+
+        let conn = Connection::open(&database).unwrap();
+        let count = |table: &str| -> Result<i64, _> {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+        };
+        let notes = count(&duckdb_table_name("joplin", ".", "notes"));
+        let items = count(&duckdb_table_name("zotero", "__", "items"));
+        let items_in_schema = count("zotero.items");
+        drop(conn);
+
+        assert_eq!(notes.unwrap(), 3);
+        assert_eq!(items.unwrap(), 5);
+        assert!(items_in_schema.is_err());
+    }
+
+    #[test]
+    fn test_duckdb_from_table_files() {
+        let dir = temp_dir();
+        Connection::open_in_memory()
+            .unwrap()
+            .execute_batch(&format!(
+                "COPY (SELECT range AS id FROM range(4)) TO '{dir}/orders.parquet' (FORMAT parquet);
+                 COPY (SELECT 1 AS id) TO '{dir}/orders.csv' (FORMAT csv, HEADER);
+                 COPY (SELECT 'a' AS name UNION ALL SELECT 'b') TO '{dir}/customers.csv' (FORMAT csv, HEADER);",
+                dir = dir.display()
+            ))
+            .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a table").unwrap();
+
+        let tables = find_table_files(dir.path()).unwrap();
+        let names: Vec<&str> = tables.iter().map(|t| t.table_name.as_str()).collect();
+        assert_eq!(names, ["customers", "orders"]);
+        assert!(tables[1].file_path.ends_with("orders.parquet"));
+
+        let database = dir.path().join("database.duckdb");
+        let failures = write_parquet_files_to_duckdb_table(
+            tables,
+            "main",
+            &database,
+            &duckdb_options(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        let conn = Connection::open(&database).unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        let (orders, customers) = (count("orders"), count("customers"));
+        drop(conn);
+
+        assert!(failures.is_empty());
+        assert_eq!(orders, 4);
+        assert_eq!(customers, 2);
+    }
+
+    #[test]
+    fn test_duckdb_load_failures_are_returned() {
+        let dir = temp_dir();
+        let good = dir.path().join("good.parquet");
+        Connection::open_in_memory()
+            .unwrap()
+            .execute_batch(&format!(
+                "COPY (SELECT 1 AS id) TO '{}' (FORMAT parquet);",
+                good.display()
+            ))
+            .unwrap();
+        let corrupt = dir.path().join("corrupt.parquet");
+        std::fs::write(&corrupt, b"not a parquet file").unwrap();
+
+        let database = dir.path().join("database.duckdb");
+        let failures = write_parquet_files_to_duckdb_table(
+            vec![
+                TableParquet {
+                    table_name: "good".to_string(),
+                    file_path: good,
+                },
+                TableParquet {
+                    table_name: "corrupt".to_string(),
+                    file_path: corrupt,
+                },
+            ],
+            "db",
+            &database,
+            &duckdb_options(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        let rows: i64 = Connection::open(&database)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM db.good", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].table_name, "corrupt");
+        assert_eq!(rows, 1);
+    }
+}
 
 #[cfg(feature = "duckdb")]
 #[derive(Debug)]
@@ -11,6 +356,7 @@ pub enum DuckDBError {
     ConnectionError(duckdb::Error),
     ExecutionError(duckdb::Error),
     InvalidPathError(String),
+    ConversionError(String),
 }
 
 #[cfg(feature = "duckdb")]
@@ -21,6 +367,9 @@ impl std::fmt::Display for DuckDBError {
             DuckDBError::ExecutionError(e) => write!(f, "Failed to execute DuckDB query: {}", e),
             #[allow(dead_code)]
             DuckDBError::InvalidPathError(p) => write!(f, "Invalid path provided: {}", p),
+            DuckDBError::ConversionError(e) => {
+                write!(f, "Failed to convert data for DuckDB: {}", e)
+            }
         }
     }
 }
@@ -28,6 +377,24 @@ impl std::fmt::Display for DuckDBError {
 #[cfg(feature = "duckdb")]
 impl std::error::Error for DuckDBError {}
 
+/// Resource limits applied to the DuckDB database while loading, DuckDB's own defaults when `None`
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(feature = "duckdb"), allow(dead_code))]
+pub struct DuckDBLimits {
+    /// `PRAGMA memory_limit`, e.g. `4GB`
+    pub memory_limit: Option<String>,
+    /// `PRAGMA threads`
+    pub threads: Option<NonZeroUsize>,
+}
+
+/// A table that couldn't be loaded into DuckDB, so is missing from the database
+#[cfg(feature = "duckdb")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuckDBLoadFailure {
+    pub table_name: String,
+    pub error: String,
+}
+
 /// Writes multiple Parquet files to tables in a DuckDB database.
 ///
 /// # Arguments
@@ -35,11 +402,15 @@ impl std::error::Error for DuckDBError {}
 /// * `parquet_paths` - Vector of TableParquet structs containing file paths and table names
 /// * `schema` - The schema name to use in DuckDB (will be sanitized)
 /// * `file_location` - Path where the DuckDB database file should be created
+/// * `options` - The separator, whether to create views instead of tables, the memory
+///   and thread limits and any SQL to run once the tables are loaded
+/// * `table_configs` - The settings of each table, keyed by its exported name, used
+///   to add any `duckdb_primary_key` and `duckdb_indexes`
 ///
 /// # Returns
 ///
-/// * `Ok(())` if all operations completed successfully
-/// * `Err(DuckDBError)` if there were any errors during the process
+/// * `Ok(failures)` with the tables that couldn't be loaded, empty if all of them were
+/// * `Err(DuckDBError)` if the database couldn't be opened or the schema created
 ///
 /// # Notes
 ///
@@ -47,6 +418,12 @@ impl std::error::Error for DuckDBError {}
 /// - Creates the schema if it doesn't exist
 /// - Creates or replaces tables for each Parquet file
 /// - Tables will be named according to the table names in the TableParquet struct
+/// - Tables are loaded in parallel, each worker on its own connection
+/// - Primary keys and indexes are added after each table is created
+/// - The `post_load_sql` statements run last, in order, each failure is logged
+/// - Views read the parquet files by their absolute path, so they resolve
+///   wherever the DuckDB file is opened from, but break if the export moves.
+///   Views can't be indexed.
 ///
 /// # Examples
 ///
@@ -70,8 +447,9 @@ pub fn write_parquet_files_to_duckdb_table(
     parquet_paths: Vec<TableParquet>,
     schema: &str,
     file_location: &Path,
-    separator: Option<&str>,
-) -> Result<(), DuckDBError> {
+    options: &DuckDBExportOptions,
+    table_configs: &HashMap<&str, &TableConfig>,
+) -> Result<Vec<DuckDBLoadFailure>, DuckDBError> {
     // Don't remove the File as this is called for each item in the config
     // This replaces the table anyway, SQLite only writes as needed
     // So this might be kinder to disk usage
@@ -82,50 +460,543 @@ pub fn write_parquet_files_to_duckdb_table(
     let schema = &sanitize_schema(schema);
 
     // Choose the separator (i.e. Schema or __ etc.)
-    let sep = separator.unwrap_or(".");
+    let sep = options.separator.as_deref().unwrap_or(".");
+    let views = options.views;
 
     // Open a connection
     // NOTE map to a connection error as PathBuf probably fixed the path
     let duckdb_conn =
         Connection::open(PathBuf::from(file_location)).map_err(DuckDBError::ConnectionError)?;
+    // Shared by the connections cloned from this one
+    apply_limits(&duckdb_conn, &options.limits)?;
 
     // Create the Schema if it doesn't exist
     create_schema(schema, &duckdb_conn)?;
 
-    for parquet_path in parquet_paths {
-        // Change into the directory
-        match parquet_path.file_path.to_str() {
-            Some(path_str) => {
-                let query = &format!(
-                    // Evaluate whether we want schema or simply __
-                    // PITA in the CLI to use schema
-                    "CREATE OR REPLACE TABLE {schema}{sep}{} AS SELECT * FROM '{}';",
-                    &parquet_path.table_name,
-                    &path_str.to_string()
-                );
-                // println!("{query}");
-                match duckdb_conn.execute(
-                    // https://duckdb.org/docs/data/parquet/overview.html
-                    query,
-                    [],
-                ) {
-                    Ok(_n) => {}
-                    Err(e) => eprintln!(
-                        "ERROR! Unable to execute SQL Query for table {}\n from path {}\n{}",
-                        parquet_path.table_name, path_str, e
-                    ),
+    // Each worker loads the next table on its own connection to the same database,
+    // the schema already exists so the tables can be created in any order
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(parquet_paths.len());
+    let connections = (0..workers)
+        .map(|_| duckdb_conn.try_clone())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(DuckDBError::ConnectionError)?;
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for conn in connections {
+            let (parquet_paths, next, results) = (&parquet_paths, &next, &results);
+            scope.spawn(move || {
+                while let Some(parquet_path) =
+                    parquet_paths.get(next.fetch_add(1, Ordering::SeqCst))
+                {
+                    let result =
+                        load_parquet_file(&conn, schema, sep, views, parquet_path, table_configs);
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(result);
                 }
+            });
+        }
+    });
+    let mut failures: Vec<DuckDBLoadFailure> = results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+    failures.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+
+    if !failures.is_empty() {
+        error!(
+            "{} of the tables in schema {schema} couldn't be loaded, {} is incomplete",
+            failures.len(),
+            display_path(file_location)
+        );
+    }
+
+    run_post_load_sql(&duckdb_conn, options);
+    Ok(failures)
+}
+
+/// Runs the `post_load_sql` statements, then the `post_load_sql_file`, logging any that fail.
+///
+/// # Returns
+///
+/// The number of statements that failed
+#[cfg(feature = "duckdb")]
+fn run_post_load_sql(conn: &Connection, options: &DuckDBExportOptions) -> usize {
+    let mut failed = 0;
+    for sql in &options.post_load_sql {
+        if let Err(e) = conn.execute_batch(sql) {
+            error!("Post-load SQL failed:\n{sql}\n{e}");
+            failed += 1;
+        }
+    }
+    if let Some(path) = &options.post_load_sql_file {
+        // The whole file is run as one batch
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|sql| conn.execute_batch(&sql).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            error!("Post-load SQL file {} failed:\n{e}", display_path(path));
+            failed += 1;
+        }
+    }
+    failed
+}
+
+/// Sets the configured `memory_limit` and `threads` pragmas on a DuckDB database
+#[cfg(feature = "duckdb")]
+fn apply_limits(conn: &Connection, limits: &DuckDBLimits) -> Result<(), DuckDBError> {
+    if let Some(memory_limit) = &limits.memory_limit {
+        conn.execute_batch(&format!(
+            "PRAGMA memory_limit = '{}';",
+            memory_limit.replace('\'', "''")
+        ))
+        .map_err(DuckDBError::ExecutionError)?;
+    }
+    if let Some(threads) = limits.threads {
+        conn.execute_batch(&format!("PRAGMA threads = {threads};"))
+            .map_err(DuckDBError::ExecutionError)?;
+    }
+    Ok(())
+}
+
+/// Creates the DuckDB table, or view, of a single parquet file, along with any
+/// primary key and indexes configured for it
+#[cfg(feature = "duckdb")]
+fn load_parquet_file(
+    duckdb_conn: &Connection,
+    schema: &str,
+    sep: &str,
+    views: bool,
+    parquet_path: &TableParquet,
+    table_configs: &HashMap<&str, &TableConfig>,
+) -> Result<(), DuckDBLoadFailure> {
+    let (kind, other_kind) = if views {
+        ("VIEW", "TABLE")
+    } else {
+        ("TABLE", "VIEW")
+    };
+
+    // A view is resolved each time it's queried, relative to the working
+    // directory rather than the DuckDB file, so it needs an absolute path
+    let file_path = if views {
+        std::fs::canonicalize(&parquet_path.file_path)
+            .unwrap_or_else(|_| parquet_path.file_path.clone())
+    } else {
+        parquet_path.file_path.clone()
+    };
+    let Some(path_str) = file_path.to_str() else {
+        error!(
+            "Unable to get path string from {}",
+            display_path(&parquet_path.file_path)
+        );
+        return Err(DuckDBLoadFailure {
+            table_name: parquet_path.table_name.clone(),
+            error: format!("Invalid path {}", display_path(&parquet_path.file_path)),
+        });
+    };
+
+    // Partitioned tables are a directory of hive style partitions
+    let source = if file_path.is_dir() {
+        format!("read_parquet('{path_str}/**/*.parquet', hive_partitioning = true)")
+    } else {
+        format!("'{path_str}'")
+    };
+    let name = duckdb_table_name(schema, sep, &parquet_path.table_name);
+    // Switching between tables and views needs the old object dropped first,
+    // this fails harmlessly when it is already the right kind
+    let _ = duckdb_conn.execute(&format!("DROP {other_kind} IF EXISTS {name}"), []);
+    let query = &format!(
+        // Evaluate whether we want schema or simply __
+        // PITA in the CLI to use schema
+        "CREATE OR REPLACE {kind} {name} AS SELECT * FROM {source};"
+    );
+    // https://duckdb.org/docs/data/parquet/overview.html
+    if let Err(e) = duckdb_conn.execute(query, []) {
+        error!(
+            "Unable to execute SQL Query for table {}\n from path {}\n{}",
+            parquet_path.table_name, path_str, e
+        );
+        return Err(DuckDBLoadFailure {
+            table_name: parquet_path.table_name.clone(),
+            error: e.to_string(),
+        });
+    }
+
+    if let Some(table_config) = table_configs
+        .get(&*parquet_path.table_name)
+        .filter(|_| !views)
+    {
+        create_table_indexes(
+            duckdb_conn,
+            schema,
+            sep,
+            &parquet_path.table_name,
+            table_config,
+        );
+    }
+    Ok(())
+}
+
+/// Adds a table's configured primary key and indexes to its DuckDB table.
+///
+/// Each statement that fails, e.g. because a column doesn't exist or the key
+/// isn't unique, is logged and the rest are still attempted.
+#[cfg(feature = "duckdb")]
+fn create_table_indexes(
+    conn: &Connection,
+    schema: &str,
+    sep: &str,
+    table: &str,
+    table_config: &TableConfig,
+) {
+    let table_sql = duckdb_table_name(schema, sep, table);
+    let column_list = |columns: &[String]| {
+        columns
+            .iter()
+            .map(|c| format!("\"{}\"", c.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut statements = Vec::new();
+    if let Some(columns) = &table_config.duckdb_primary_key {
+        statements.push(format!(
+            "ALTER TABLE {table_sql} ADD PRIMARY KEY ({})",
+            column_list(columns)
+        ));
+    }
+    for columns in table_config.duckdb_indexes.iter().flatten() {
+        statements.push(format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {table_sql} ({})",
+            duckdb_index_name(schema, sep, table, columns),
+            column_list(columns)
+        ));
+    }
+
+    for statement in statements {
+        if let Err(e) = conn.execute(&statement, []) {
+            error!("Unable to index table {table}\n{statement}\n{e}");
+        }
+    }
+}
+
+/// Returns the quoted name of an index, unique within its schema, e.g. `"album_artist_id_idx"`
+#[cfg(feature = "duckdb")]
+fn duckdb_index_name(schema: &str, sep: &str, table: &str, columns: &[String]) -> String {
+    let table = match sep {
+        // Indexes live in their table's schema
+        "." => table.to_string(),
+        _ => format!("{schema}{sep}{table}"),
+    };
+    format!(
+        "\"{}_{}_idx\"",
+        table.replace('"', "\"\""),
+        columns.join("_").replace('"', "\"\"")
+    )
+}
+
+/// DuckDB's `STANDARD_VECTOR_SIZE`, the most rows the `arrow` table function reads in one call
+#[cfg(feature = "duckdb")]
+const DUCKDB_VECTOR_SIZE: usize = 2048;
+
+/// A DuckDB database that `--format duckdb-only` loads tables into as they are read.
+///
+/// Each DataFrame is handed to DuckDB as Arrow record batches, through its `arrow`
+/// table function, so no table is written to (and read back from) a file. Tables are
+/// loaded one at a time over a single connection.
+#[cfg(feature = "duckdb")]
+pub struct DuckDBLoader {
+    conn: Mutex<Connection>,
+    schema: String,
+    sep: String,
+}
+
+#[cfg(feature = "duckdb")]
+impl DuckDBLoader {
+    /// Opens (or creates) the database at `file_location`, applying the limits in
+    /// `options` and creating the schema
+    pub fn open(
+        file_location: &Path,
+        schema: &str,
+        options: &DuckDBExportOptions,
+    ) -> Result<Self, DuckDBError> {
+        let schema = sanitize_schema(schema);
+        let conn =
+            Connection::open(PathBuf::from(file_location)).map_err(DuckDBError::ConnectionError)?;
+        apply_limits(&conn, &options.limits)?;
+        create_schema(&schema, &conn)?;
+        conn.register_table_function::<ArrowVTab>("arrow")
+            .map_err(DuckDBError::ExecutionError)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            schema,
+            sep: options.separator.clone().unwrap_or_else(|| ".".to_string()),
+        })
+    }
+
+    /// Loads a DataFrame into the table `table`, replacing any table or view of the
+    /// same name. With `append` the rows are added to the table instead, e.g. for
+    /// each batch after the first of a table read in batches.
+    ///
+    /// The rows are inserted in one transaction, so a failure leaves no partial table.
+    pub fn load(&self, df: &mut DataFrame, table: &str, append: bool) -> Result<(), DuckDBError> {
+        let batches = dataframe_to_record_batches(df)?;
+        let name = duckdb_table_name(&self.schema, &self.sep, table);
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Outside the transaction, as this fails harmlessly when it's already a table
+        if !append {
+            let _ = conn.execute(&format!("DROP VIEW IF EXISTS {name}"), []);
+        }
+        let transaction = conn.transaction().map_err(DuckDBError::ExecutionError)?;
+        if !append {
+            // Only the columns, the rows are inserted below
+            let empty = RecordBatch::new_empty(batches.schema.clone());
+            transaction
+                .execute(
+                    &format!("CREATE OR REPLACE TABLE {name} AS SELECT * FROM arrow(?, ?)"),
+                    arrow_recordbatch_to_query_params(empty),
+                )
+                .map_err(DuckDBError::ExecutionError)?;
+        }
+        for batch in &batches.batches {
+            for offset in (0..batch.num_rows()).step_by(DUCKDB_VECTOR_SIZE) {
+                let rows = DUCKDB_VECTOR_SIZE.min(batch.num_rows() - offset);
+                transaction
+                    .execute(
+                        &format!("INSERT INTO {name} SELECT * FROM arrow(?, ?)"),
+                        arrow_recordbatch_to_query_params(batch.slice(offset, rows)),
+                    )
+                    .map_err(DuckDBError::ExecutionError)?;
             }
-            None => eprintln!(
-                "Unable to get path string from {:?}",
-                parquet_path.file_path
+        }
+        transaction.commit().map_err(DuckDBError::ExecutionError)
+    }
+
+    /// Adds the configured primary keys and indexes to the loaded `tables`, then
+    /// runs the `post_load_sql`
+    ///
+    /// * `table_configs` - The settings of each table, keyed by its exported name
+    pub fn finish(
+        self,
+        tables: &[&str],
+        options: &DuckDBExportOptions,
+        table_configs: &HashMap<&str, &TableConfig>,
+    ) {
+        let conn = self.conn.into_inner().unwrap_or_else(|e| e.into_inner());
+        for table in tables {
+            if let Some(table_config) = table_configs.get(table) {
+                create_table_indexes(&conn, &self.schema, &self.sep, table, table_config);
+            }
+        }
+        run_post_load_sql(&conn, options);
+    }
+}
+
+/// The record batches of a DataFrame, along with their schema for when there are none
+#[cfg(feature = "duckdb")]
+struct RecordBatches {
+    schema: duckdb::arrow::datatypes::SchemaRef,
+    batches: Vec<RecordBatch>,
+}
+
+/// Converts a polars DataFrame to the Arrow record batches DuckDB reads.
+///
+/// Polars and DuckDB use different Arrow implementations, so the DataFrame is
+/// passed between them as an in-memory Arrow IPC stream.
+#[cfg(feature = "duckdb")]
+fn dataframe_to_record_batches(df: &mut DataFrame) -> Result<RecordBatches, DuckDBError> {
+    let mut buffer = Vec::new();
+    // DuckDB's arrow table function predates the string and binary view types
+    IpcStreamWriter::new(&mut buffer)
+        .with_compat_level(CompatLevel::oldest())
+        .finish(df)
+        .map_err(|e| DuckDBError::ConversionError(e.to_string()))?;
+    let reader = StreamReader::try_new(std::io::Cursor::new(buffer), None)
+        .map_err(|e| DuckDBError::ConversionError(e.to_string()))?;
+    let schema = reader.schema();
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| DuckDBError::ConversionError(e.to_string()))?;
+    Ok(RecordBatches { schema, batches })
+}
+
+/// Copies tables from a SQLite database straight into a DuckDB database.
+///
+/// This uses DuckDB's `sqlite` extension to `ATTACH` the SQLite file, which avoids
+/// writing (and then reading back) an intermediate parquet file for every table.
+///
+/// # Arguments
+///
+/// * `sqlite_path` - Path to the SQLite database file
+/// * `tables` - Names of the tables to copy
+/// * `schema` - The schema name to use in DuckDB (will be sanitized)
+/// * `file_location` - Path where the DuckDB database file should be created
+/// * `separator` - Separator between schema and table name, defaults to `.`
+///
+/// # Returns
+///
+/// * `Ok(())` if the SQLite database could be attached, individual table failures are reported
+/// * `Err(DuckDBError)` if the database couldn't be opened or attached, e.g. because
+///   the `sqlite` extension isn't installed and can't be downloaded
+#[cfg(feature = "duckdb")]
+pub fn copy_sqlite_tables_to_duckdb(
+    sqlite_path: &Path,
+    tables: &[String],
+    schema: &str,
+    file_location: &Path,
+    separator: Option<&str>,
+) -> Result<(), DuckDBError> {
+    // Sanitize the Schema
+    let schema = &sanitize_schema(schema);
+
+    // Choose the separator (i.e. Schema or __ etc.)
+    let sep = separator.unwrap_or(".");
+
+    let sqlite_path = sqlite_path
+        .to_str()
+        .ok_or_else(|| DuckDBError::InvalidPathError(display_path(sqlite_path)))?;
+
+    // Open a connection
+    let duckdb_conn =
+        Connection::open(PathBuf::from(file_location)).map_err(DuckDBError::ConnectionError)?;
+
+    // Create the Schema if it doesn't exist
+    create_schema(schema, &duckdb_conn)?;
+
+    load_sqlite_extension(&duckdb_conn)?;
+
+    // Attach the SQLite database
+    duckdb_conn
+        .execute_batch(&format!(
+            "ATTACH '{}' AS {SQLITE_ALIAS} (TYPE sqlite, READ_ONLY);",
+            sqlite_path.replace('\'', "''")
+        ))
+        .map_err(DuckDBError::ExecutionError)?;
+
+    for table in tables {
+        let query = format!(
+            "CREATE OR REPLACE TABLE {} AS SELECT * FROM {SQLITE_ALIAS}.\"{}\";",
+            duckdb_table_name(schema, sep, table),
+            table.replace('"', "\"\"")
+        );
+        match duckdb_conn.execute(&query, []) {
+            Ok(_n) => info!("Copied {table} directly from SQLite into DuckDB"),
+            Err(e) => error!(
+                "Unable to copy table {} from SQLite {}\n{}",
+                table, sqlite_path, e
             ),
-        };
+        }
     }
 
+    duckdb_conn
+        .execute(&format!("DETACH {SQLITE_ALIAS}"), [])
+        .map_err(DuckDBError::ExecutionError)?;
+
     Ok(())
 }
 
+/// Loads DuckDB's `sqlite` extension, installing it first only if it isn't already.
+///
+/// The extension is only downloaded when it's missing, so an offline machine
+/// with it installed can still read and write SQLite files.
+#[cfg(feature = "duckdb")]
+fn load_sqlite_extension(conn: &Connection) -> Result<(), DuckDBError> {
+    if conn.execute_batch("LOAD sqlite;").is_err() {
+        conn.execute_batch("INSTALL sqlite; LOAD sqlite;")
+            .map_err(DuckDBError::ExecutionError)?;
+    }
+    Ok(())
+}
+
+/// Appends the outcome of each table in a run to a SQLite run-log database.
+///
+/// The database and its `table_exports` table are created if they don't exist,
+/// so the log accumulates across runs and can be queried for history.
+///
+/// # Arguments
+///
+/// * `log_path` - Path to the SQLite run-log database
+/// * `run_id` - The identifier of the current run
+/// * `database` - The name of the database the tables were exported from
+/// * `outcomes` - The outcome of every table that was attempted
+#[cfg(feature = "duckdb")]
+pub fn append_run_log(
+    log_path: &Path,
+    run_id: &str,
+    database: &str,
+    outcomes: &[TableOutcome],
+) -> Result<(), DuckDBError> {
+    let log_path = log_path
+        .to_str()
+        .ok_or_else(|| DuckDBError::InvalidPathError(display_path(log_path)))?;
+
+    // DuckDB writes the SQLite file through its sqlite extension
+    let duckdb_conn = Connection::open_in_memory().map_err(DuckDBError::ConnectionError)?;
+    load_sqlite_extension(&duckdb_conn)?;
+    duckdb_conn
+        .execute_batch(&format!(
+            "ATTACH '{}' AS {RUN_LOG_ALIAS} (TYPE sqlite);
+             CREATE TABLE IF NOT EXISTS {RUN_LOG_ALIAS}.table_exports (
+                 run_id TEXT,
+                 timestamp TEXT,
+                 database TEXT,
+                 table_name TEXT,
+                 status TEXT,
+                 rows BIGINT,
+                 duration_secs DOUBLE
+             );",
+            log_path.replace('\'', "''")
+        ))
+        .map_err(DuckDBError::ExecutionError)?;
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    for outcome in outcomes {
+        duckdb_conn
+            .execute(
+                &format!("INSERT INTO {RUN_LOG_ALIAS}.table_exports VALUES (?, ?, ?, ?, ?, ?, ?)"),
+                duckdb::params![
+                    run_id,
+                    timestamp,
+                    database,
+                    outcome.table_name,
+                    outcome.status.label(),
+                    outcome.rows().map(|r| r as i64),
+                    outcome.duration.as_secs_f64(),
+                ],
+            )
+            .map_err(DuckDBError::ExecutionError)?;
+    }
+
+    Ok(())
+}
+
+/// The name the run-log SQLite database is attached under in DuckDB
+#[cfg(feature = "duckdb")]
+const RUN_LOG_ALIAS: &str = "run_log";
+
+/// The name the source SQLite database is attached under in DuckDB
+#[cfg(feature = "duckdb")]
+const SQLITE_ALIAS: &str = "sqlite_source";
+
+/// Returns the quoted name of a table in DuckDB, either `schema."table"` or, with
+/// a custom separator, a single identifier like `"schema__table"`
+pub fn duckdb_table_name(schema: &str, sep: &str, table: &str) -> String {
+    match sep {
+        "." => format!("{schema}.\"{}\"", table.replace('"', "\"\"")),
+        _ => format!(
+            "\"{}\"",
+            format!("{schema}{sep}{table}").replace('"', "\"\"")
+        ),
+    }
+}
+
 #[cfg(feature = "duckdb")]
 pub fn create_schema(schema: &str, conn: &Connection) -> Result<(), DuckDBError> {
     let schema = &sanitize_schema(schema);
@@ -1,5 +1,174 @@
+use crate::cli::OutputFormat;
 use crate::file_helpers::sanitize_schema;
-use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::temp_dir;
+
+    #[test]
+    fn test_prune_stale_files() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.path().join("partitioned")).unwrap();
+        for file in [
+            "notes.parquet",
+            "tags.parquet",
+            "dropped.parquet",
+            "notes.csv",
+        ] {
+            std::fs::File::create(dir.path().join(file)).unwrap();
+        }
+
+        let pruned =
+            prune_stale_files(dir.path(), &["notes", "tags"], OutputFormat::Parquet).unwrap();
+        let mut remaining: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+
+        assert_eq!(pruned, [dir.path().join("dropped.parquet")]);
+        assert_eq!(
+            remaining,
+            ["notes.csv", "notes.parquet", "partitioned", "tags.parquet"]
+        );
+
+        // A table's schema isn't mistaken for a stale JSON export
+        let dir = temp_dir();
+        for file in ["notes.json", "notes.schema.json", "dropped.json"] {
+            std::fs::File::create(dir.path().join(file)).unwrap();
+        }
+        let pruned = prune_stale_files(dir.path(), &["notes"], OutputFormat::Json).unwrap();
+        assert_eq!(pruned, [dir.path().join("dropped.json")]);
+    }
+
+    #[test]
+    fn test_table_names_are_safe_file_names() {
+        // Ordinary names, including schema-qualified ones, are unchanged
+        for name in ["notes", "Order Items", "hr.staff", "données"] {
+            assert_eq!(sanitize_file_name(name), name);
+        }
+        assert_eq!(sanitize_file_name("a/b"), "a_b");
+        assert_eq!(sanitize_file_name("..\\..\\evil"), ".._.._evil");
+        assert_eq!(sanitize_file_name("../etc/passwd"), ".._etc_passwd");
+        assert_eq!(sanitize_file_name("c:d*e?\"<f>|"), "c_d_e___f__");
+        assert_eq!(sanitize_file_name("tab\tle"), "tab_le");
+        assert_eq!(sanitize_file_name(".."), "__");
+        assert_eq!(sanitize_file_name("notes. "), "notes__");
+        assert_eq!(sanitize_file_name("CON"), "CON_");
+        assert_eq!(sanitize_file_name("nul.archive"), "nul_.archive");
+        assert_eq!(sanitize_file_name("lpt9"), "lpt9_");
+        assert_eq!(sanitize_file_name("CONTACTS"), "CONTACTS");
+        assert_eq!(sanitize_file_name(""), "table");
+
+        // The file always lands directly in the schema directory
+        let directory = Path::new("/exports");
+        for name in ["a/b", "../../escape", "C:\\Windows\\evil", "CON", ".."] {
+            let path = output_filepath(name, directory, "joplin", OutputFormat::Parquet);
+            assert_eq!(path.parent(), Some(directory.join("joplin").as_path()));
+            let dirpath = build_output_dirpath(name, directory, "joplin");
+            assert_eq!(dirpath.parent(), Some(directory.join("joplin").as_path()));
+        }
+        assert_eq!(
+            output_filepath("CON", directory, "joplin", OutputFormat::Csv),
+            directory.join("joplin").join("CON_.csv")
+        );
+
+        // Stale file pruning matches tables by their sanitized names
+        let dir = temp_dir();
+        for file in ["a_b.parquet", "CON_.parquet"] {
+            std::fs::File::create(dir.path().join(file)).unwrap();
+        }
+        let pruned = prune_stale_files(dir.path(), &["a/b", "CON"], OutputFormat::Parquet).unwrap();
+        assert!(pruned.is_empty());
+    }
+
+    #[test]
+    fn test_display_path() {
+        let native = |parts: &[&str]| parts.join(&MAIN_SEPARATOR.to_string());
+        let path = Path::new("data/raw")
+            .join("sql_server")
+            .join("Table.parquet");
+        assert_eq!(
+            display_path(&path),
+            native(&["data", "raw", "sql_server", "Table.parquet"])
+        );
+        // Unlike `{:?}`, there are no quotes and nothing is escaped
+        assert_eq!(display_path(Path::new("notes.parquet")), "notes.parquet");
+        assert_eq!(
+            display_path(Path::new("/exports/./joplin//notes.parquet")),
+            format!(
+                "{MAIN_SEPARATOR}{}",
+                native(&["exports", "joplin", "notes.parquet"])
+            )
+        );
+        assert_eq!(display_path(Path::new("")), "");
+
+        // Mixed separators only arise where both are separators
+        #[cfg(windows)]
+        {
+            assert_eq!(
+                display_path(Path::new("data/raw\\sql_server\\Table.parquet")),
+                "data\\raw\\sql_server\\Table.parquet"
+            );
+            assert_eq!(
+                display_path(Path::new("C:/exports\\notes.parquet")),
+                "C:\\exports\\notes.parquet"
+            );
+            assert_eq!(
+                display_path(Path::new("C:notes.parquet")),
+                "C:notes.parquet"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ensure_free_space() {
+        let dir = Path::new("/exports");
+        let free = |bytes: u64| move |_: &Path| Ok(bytes);
+
+        assert!(ensure_free_space(dir, 10 * 1024 * 1024, free(20 * 1024 * 1024)).is_ok());
+        let error = ensure_free_space(dir, 30 * 1024 * 1024, free(20 * 1024 * 1024)).unwrap_err();
+        assert!(error.starts_with("Not enough free space in /exports"));
+        assert!(error.contains("needs about 30.0 MiB but only 20.0 MiB is free"));
+
+        // Not knowing is an error too, rather than exporting blind
+        let unreadable = |_: &Path| Err(std::io::Error::other("unsupported filesystem"));
+        assert!(ensure_free_space(dir, 0, unreadable)
+            .unwrap_err()
+            .contains("unsupported filesystem"));
+    }
+
+    #[test]
+    fn test_snapshot_directories_are_distinct() {
+        let dir = temp_dir();
+        let time = Local::now();
+
+        // Two runs in the same second
+        let first = create_snapshot_directory(dir.path(), time).unwrap();
+        let second = create_snapshot_directory(dir.path(), time).unwrap();
+        let first_name = first.file_name().unwrap().to_string_lossy().to_string();
+
+        #[cfg(unix)]
+        {
+            update_latest_symlink(dir.path(), &first).unwrap();
+            update_latest_symlink(dir.path(), &second).unwrap();
+            let latest = std::fs::canonicalize(dir.path().join("latest")).unwrap();
+            assert_eq!(latest, std::fs::canonicalize(&second).unwrap());
+        }
+
+        let both_exist = first.is_dir() && second.is_dir();
+        assert_ne!(first, second);
+        assert!(both_exist);
+        assert_eq!(first_name, time.format("%Y%m%d-%H%M%S").to_string());
+        assert_eq!(
+            second.file_name().unwrap().to_string_lossy(),
+            format!("{first_name}-1")
+        );
+    }
+}
 
 /// Represents a parquet file associated with a specific database table.
 #[derive(Clone)]
@@ -8,23 +177,384 @@ pub struct TableParquet {
     pub table_name: String,
 }
 impl TableParquet {
-    pub fn new(table_name: &str, directory: &Path, schema: &str) -> Self {
+    pub fn new(table_name: &str, directory: &Path, schema: &str, format: OutputFormat) -> Self {
         Self {
-            file_path: build_output_filepath(table_name, directory, schema),
+            file_path: build_output_filepath(table_name, directory, schema, format),
             table_name: String::from(table_name),
         }
     }
+
+    /// Creates a `TableParquet` like `new`, but without creating the schema directory
+    pub fn planned(table_name: &str, directory: &Path, schema: &str, format: OutputFormat) -> Self {
+        Self {
+            file_path: output_filepath(table_name, directory, schema, format),
+            table_name: String::from(table_name),
+        }
+    }
+
+    /// Creates a `TableParquet` whose `file_path` is a directory of hive partitioned files
+    pub fn new_partitioned(table_name: &str, directory: &Path, schema: &str) -> Self {
+        Self {
+            file_path: build_output_dirpath(table_name, directory, schema),
+            table_name: String::from(table_name),
+        }
+    }
+}
+
+/// Finds previously exported parquet files underneath an export directory.
+///
+/// Each subdirectory of `directory` is treated as a schema. Within a schema,
+/// `<table>.parquet` files and `<table>/` directories of hive partitioned files
+/// are returned as tables.
+///
+/// # Returns
+///
+/// A vector of `(schema, tables)` pairs, sorted by schema name.
+pub fn find_table_parquets(directory: &Path) -> std::io::Result<Vec<(String, Vec<TableParquet>)>> {
+    let mut schemas = Vec::new();
+    for schema_entry in std::fs::read_dir(directory)? {
+        let schema_path = schema_entry?.path();
+        if !schema_path.is_dir() {
+            continue;
+        }
+        let Some(schema) = schema_path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        let mut tables = Vec::new();
+        for table_entry in std::fs::read_dir(&schema_path)? {
+            let file_path = table_entry?.path();
+            let is_parquet = file_path.extension().is_some_and(|ext| ext == "parquet");
+            if !(is_parquet || is_partitioned_dir(&file_path)) {
+                continue;
+            }
+            if let Some(table_name) = file_path.file_stem().and_then(|n| n.to_str()) {
+                tables.push(TableParquet {
+                    table_name: table_name.to_string(),
+                    file_path: file_path.clone(),
+                });
+            }
+        }
+
+        if !tables.is_empty() {
+            tables.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+            schemas.push((schema.to_string(), tables));
+        }
+    }
+    schemas.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(schemas)
+}
+
+/// The extensions of the files `find_table_files` treats as tables, DuckDB reads each by its extension
+const TABLE_FILE_EXTENSIONS: [&str; 2] = ["parquet", "csv"];
+
+/// Finds the parquet and CSV files directly inside a directory, e.g. extracts
+/// written by another tool. Each file is a table named after the file.
+///
+/// Directories of hive partitioned files are returned as tables too. A table with
+/// both a parquet and a CSV file is read from the parquet file.
+///
+/// # Returns
+///
+/// The tables, sorted by name
+pub fn find_table_files(directory: &Path) -> std::io::Result<Vec<TableParquet>> {
+    let mut tables = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let file_path = entry?.path();
+        let is_table_file = file_path.is_file()
+            && file_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| TABLE_FILE_EXTENSIONS.contains(&ext));
+        if !(is_table_file || is_partitioned_dir(&file_path)) {
+            continue;
+        }
+        if let Some(table_name) = file_path.file_stem().and_then(|n| n.to_str()) {
+            tables.push(TableParquet {
+                table_name: table_name.to_string(),
+                file_path: file_path.clone(),
+            });
+        }
+    }
+    // The parquet file of a table sorts first, so it's the one kept
+    tables.sort_by_key(|t| {
+        let is_parquet = t.file_path.extension().is_some_and(|ext| ext == "parquet");
+        (t.table_name.clone(), !is_parquet)
+    });
+    tables.dedup_by(|a, b| a.table_name == b.table_name);
+    Ok(tables)
+}
+
+/// Whether a directory holds hive partitions (`key=value` subdirectories), rather than e.g. blobs
+fn is_partitioned_dir(path: &Path) -> bool {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries.filter_map(Result::ok).any(|entry| {
+                entry.path().is_dir() && entry.file_name().to_string_lossy().contains('=')
+            })
+        })
+        .unwrap_or(false)
 }
 
-pub fn build_output_filepath(name: &str, directory: &Path, schema: &str) -> PathBuf {
+pub fn build_output_filepath(
+    name: &str,
+    directory: &Path,
+    schema: &str,
+    format: OutputFormat,
+) -> PathBuf {
+    let filename = output_filepath(name, directory, schema, format);
+    if let Some(dirname) = filename.parent() {
+        std::fs::create_dir_all(dirname).unwrap_or_else(|e| {
+            panic!("Unable to create directory: {}\n{e}", display_path(dirname));
+        });
+    }
+    filename
+}
+
+/// Returns the path `build_output_filepath` would use, without creating any directories
+pub fn output_filepath(
+    name: &str,
+    directory: &Path,
+    schema: &str,
+    format: OutputFormat,
+) -> PathBuf {
     let schema = sanitize_schema(schema);
     let dirname = PathBuf::from(directory).join(schema);
-    std::fs::create_dir_all(&dirname).unwrap_or_else(|e| {
-        panic!("Unable to create directory: {:?}\n{e}", dirname);
-    });
 
     // Filename
-    let mut filename = PathBuf::from(format!("{name}.parquet"));
-    filename = dirname.join(&filename);
-    filename
+    let filename = PathBuf::from(format!(
+        "{}.{}",
+        sanitize_file_name(name),
+        format.extension()
+    ));
+    dirname.join(filename)
+}
+
+/// Characters Windows doesn't allow in file names, `/` and `\` also separate paths
+const UNSAFE_FILE_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names Windows reserves, with or without an extension
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes a table name safe to use as a file or directory name on any platform.
+///
+/// Unlike `sanitize_schema` only what has to change is changed, so ordinary names
+/// keep their files: path separators, characters Windows doesn't allow and control
+/// characters become `_`, as do trailing dots and spaces (which Windows drops), and
+/// reserved device names like `CON` get a `_` appended. The table keeps its
+/// original name in DuckDB, which is loaded from the `TableParquet`'s `table_name`.
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || UNSAFE_FILE_NAME_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    // This also covers `.` and `..`
+    let kept = sanitized.trim_end_matches(['.', ' ']).len();
+    let trailing = sanitized.len() - kept;
+    sanitized.replace_range(kept.., &"_".repeat(trailing));
+
+    let stem = sanitized.find('.').unwrap_or(sanitized.len());
+    if RESERVED_FILE_NAMES
+        .iter()
+        .any(|reserved| sanitized[..stem].eq_ignore_ascii_case(reserved))
+    {
+        sanitized.insert(stem, '_');
+    }
+
+    if sanitized.is_empty() {
+        "table".to_string()
+    } else {
+        sanitized
+    }
+}
+
+/// The extensions of files written next to an exported table, rather than tables themselves
+const SIDECAR_EXTENSIONS: [&str; 2] = ["profile.json", "schema.json"];
+
+/// Deletes the exported files in a schema directory that don't belong to a current table.
+///
+/// Only files with the extension of `format` directly inside `directory` are
+/// considered, partitioned table directories, the `.profile.json` and `.schema.json`
+/// files written alongside tables and other files are left alone.
+///
+/// # Arguments
+///
+/// * `directory` - The schema directory, nothing is done if it doesn't exist
+/// * `current` - The names of the tables and custom queries that are still exported
+/// * `format` - The format of the exported files
+///
+/// # Returns
+///
+/// The paths of the deleted files
+pub fn prune_stale_files(
+    directory: &Path,
+    current: &[&str],
+    format: OutputFormat,
+) -> std::io::Result<Vec<PathBuf>> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut pruned = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        let is_export = path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == format.extension())
+            && !path.file_name().is_some_and(|f| {
+                SIDECAR_EXTENSIONS
+                    .iter()
+                    .any(|ext| f.to_string_lossy().ends_with(&format!(".{ext}")))
+            });
+        if !is_export {
+            continue;
+        }
+        let is_current = current.iter().any(|name| {
+            path.file_name().is_some_and(|f| {
+                *f == *format!("{}.{}", sanitize_file_name(name), format.extension())
+            })
+        });
+        if !is_current {
+            log::info!("Removing stale file {}", display_path(&path));
+            std::fs::remove_file(&path)?;
+            pruned.push(path);
+        }
+    }
+    Ok(pruned)
+}
+
+/// Checks the filesystem of `directory` has at least `required` bytes free.
+///
+/// # Arguments
+///
+/// * `directory` - A directory on the filesystem that will be written to
+/// * `required` - The bytes the export is expected to need
+/// * `available_space` - Reads the free space of a filesystem, e.g. `fs2::available_space`
+///
+/// # Returns
+///
+/// An error describing the shortfall if there isn't enough space, or the free
+/// space couldn't be read
+pub fn ensure_free_space(
+    directory: &Path,
+    required: u64,
+    available_space: impl Fn(&Path) -> std::io::Result<u64>,
+) -> Result<(), String> {
+    let available = available_space(directory).map_err(|e| {
+        format!(
+            "Unable to read the free space of {}, drop --check-disk-space and --min-free-mb \
+             to export anyway\n{e}",
+            display_path(directory)
+        )
+    })?;
+    if available < required {
+        return Err(format!(
+            "Not enough free space in {}, the export needs about {} but only {} is free \
+             (drop --check-disk-space and --min-free-mb to export anyway)",
+            display_path(directory),
+            format_bytes(required),
+            format_bytes(available)
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a `YYYYMMDD-HHMMSS` snapshot directory for a run underneath `directory`.
+///
+/// If a snapshot with that name already exists, e.g. from a run that started
+/// in the same second, a `-1`, `-2`, ... suffix is added.
+pub fn create_snapshot_directory(
+    directory: &Path,
+    time: DateTime<Local>,
+) -> std::io::Result<PathBuf> {
+    let name = time.format("%Y%m%d-%H%M%S").to_string();
+    let mut snapshot = directory.join(&name);
+    let mut suffix = 0;
+    loop {
+        // create_dir fails if another run has claimed the name
+        match std::fs::create_dir_all(directory).and_then(|_| std::fs::create_dir(&snapshot)) {
+            Ok(()) => return Ok(snapshot),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                suffix += 1;
+                snapshot = directory.join(format!("{name}-{suffix}"));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Points the `latest` symlink in `directory` at `snapshot`, replacing any previous link
+pub fn update_latest_symlink(directory: &Path, snapshot: &Path) -> std::io::Result<()> {
+    let link = directory.join("latest");
+    // Relative, so the export directory can be moved
+    let target = snapshot.strip_prefix(directory).unwrap_or(snapshot);
+    if link.symlink_metadata().is_ok() {
+        std::fs::remove_file(&link)?;
+    }
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(target, &link);
+    #[cfg(windows)]
+    let result = std::os::windows::fs::symlink_dir(target, &link);
+    result
+}
+
+/// Returns the directory that holds the partitioned files of a table
+pub fn build_output_dirpath(name: &str, directory: &Path, schema: &str) -> PathBuf {
+    let schema = sanitize_schema(schema);
+    PathBuf::from(directory)
+        .join(schema)
+        .join(sanitize_file_name(name))
+}
+
+/// Formats a path for messages, with every separator the platform's own.
+///
+/// Paths joined onto a directory given as `data/raw` come out as
+/// `data/raw\sql_server\notes.parquet` on Windows, and `{:?}` doubles each `\`,
+/// which looks like a bug. The path is rebuilt from its components instead,
+/// without quotes or escaping.
+pub fn display_path(path: &Path) -> String {
+    let mut display = String::new();
+    let mut separate = false;
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => {
+                display.push_str(&prefix.as_os_str().to_string_lossy());
+            }
+            Component::RootDir => display.push(MAIN_SEPARATOR),
+            other => {
+                if separate {
+                    display.push(MAIN_SEPARATOR);
+                }
+                display.push_str(&other.as_os_str().to_string_lossy());
+                separate = true;
+            }
+        }
+    }
+    display
+}
+
+/// Formats a number of bytes with a binary unit suffix, e.g. `1.5 MiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
 }
@@ -0,0 +1,323 @@
+use crate::cli::LogFormat;
+use crate::progress;
+use env_logger::Target;
+use log::kv::{Key, Value, VisitSource};
+use log::{LevelFilter, Record};
+use serde_json::{Map, Number};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::temp_dir;
+    use log::{Level, Log};
+
+    #[test]
+    fn test_json_log_line() {
+        let fields = [
+            ("database", Value::from("joplin")),
+            ("table", Value::from("notes")),
+            ("rows", Value::from(30u64)),
+            ("rows_per_sec", Value::from(12.5)),
+            ("level", Value::from("overridden")),
+        ];
+        let line = json_line(
+            &Record::builder()
+                .level(Level::Info)
+                .target("database_export")
+                .args(format_args!("notes: 30 rows"))
+                .key_values(&fields)
+                .build(),
+            "2025-01-01T00:00:00Z",
+            "run-1",
+        );
+        assert!(!line.contains('\n'));
+
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!({
+                "timestamp": "2025-01-01T00:00:00Z",
+                "level": "INFO",
+                "run_id": "run-1",
+                "target": "database_export",
+                "message": "notes: 30 rows",
+                "database": "joplin",
+                "table": "notes",
+                "rows": 30,
+                "rows_per_sec": 12.5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_log_file_rotation() {
+        let dir = temp_dir();
+        let config = LogFile {
+            path: dir.path().join("logs").join("export.log"),
+            max_bytes: 200,
+            max_files: 2,
+        };
+        let writer = LogWriter {
+            console: false,
+            file: Some(RotatingFile::open(&config).unwrap()),
+        };
+        let logger = builder(LevelFilter::Info, "run-1", LogFormat::Text, writer).build();
+
+        let log = |level: Level, message: &str| {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("{message}"))
+                    .build(),
+            )
+        };
+        log(Level::Info, "Processing database: joplin");
+        log(Level::Debug, "filtered out");
+        logger.flush();
+
+        let contents = std::fs::read_to_string(&config.path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(contents.contains("INFO run-1] Processing database: joplin"));
+        assert!(!contents.contains("filtered out"));
+
+        // Each line is about 70 bytes, so the file is rotated every couple of lines
+        for n in 0..10 {
+            log(Level::Info, &format!("notes: {n} rows in 0.1s"));
+        }
+        logger.flush();
+        let rotated = |n: usize| {
+            let mut path = config.path.clone().into_os_string();
+            path.push(format!(".{n}"));
+            PathBuf::from(path)
+        };
+        assert!(rotated(1).exists());
+        assert!(rotated(2).exists());
+        assert!(!rotated(3).exists());
+        for path in [config.path.clone(), rotated(1), rotated(2)] {
+            let size = std::fs::metadata(&path).unwrap().len();
+            assert!(
+                size <= config.max_bytes,
+                "{} is {size} bytes",
+                path.display()
+            );
+        }
+        let newest = std::fs::read_to_string(&config.path).unwrap();
+        assert!(newest.contains("notes: 9 rows in 0.1s"));
+    }
+}
+
+/// Where log lines are written besides the console, see `--log-file`
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    pub path: PathBuf,
+    /// The size a file may grow to before it's rotated
+    pub max_bytes: u64,
+    /// The number of rotated files kept, `<path>.1` being the newest
+    pub max_files: usize,
+}
+
+/// Initialises the logger, every line is tagged with the run ID.
+///
+/// # Arguments
+///
+/// * `level` - The default level, `RUST_LOG` overrides this when set
+/// * `run_id` - The identifier of the current run
+/// * `format` - Human readable lines, or one JSON object per line
+/// * `file` - A file to append log lines to, rotated by size
+/// * `console` - Whether log lines are also written to stderr
+pub fn init(
+    level: LevelFilter,
+    run_id: &str,
+    format: LogFormat,
+    file: Option<&LogFile>,
+    console: bool,
+) -> std::io::Result<()> {
+    let writer = LogWriter {
+        console,
+        file: file.map(RotatingFile::open).transpose()?,
+    };
+    builder(level, run_id, format, writer)
+        .parse_default_env()
+        .init();
+    Ok(())
+}
+
+/// A logger builder writing formatted lines to `writer`
+fn builder(
+    level: LevelFilter,
+    run_id: &str,
+    format: LogFormat,
+    writer: LogWriter,
+) -> env_logger::Builder {
+    let run_id = run_id.to_string();
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(level)
+        .target(Target::Pipe(Box::new(writer)))
+        .format(move |buf, record| match format {
+            LogFormat::Text => writeln!(
+                buf,
+                "[{} {} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                run_id,
+                record.args()
+            ),
+            LogFormat::Json => {
+                let timestamp = buf.timestamp().to_string();
+                writeln!(buf, "{}", json_line(record, &timestamp, &run_id))
+            }
+        });
+    builder
+}
+
+/// A log record as a single line JSON object, with the structured fields of the
+/// record (e.g. `info!(table = name; ...)`) alongside the message.
+///
+/// Fields named like one of the standard keys don't replace it.
+pub fn json_line(record: &Record, timestamp: &str, run_id: &str) -> String {
+    let mut fields = JsonFields(Map::new());
+    fields.0.insert("timestamp".into(), timestamp.into());
+    fields
+        .0
+        .insert("level".into(), record.level().as_str().into());
+    fields.0.insert("run_id".into(), run_id.into());
+    fields.0.insert("target".into(), record.target().into());
+    fields
+        .0
+        .insert("message".into(), record.args().to_string().into());
+    // Only fails if a visitor does, which this one doesn't
+    let _ = record.key_values().visit(&mut fields);
+    serde_json::Value::Object(fields.0).to_string()
+}
+
+/// Collects the structured fields of a record, numbers and booleans keep their type
+struct JsonFields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.entry(key.as_str()).or_insert(value);
+        Ok(())
+    }
+}
+
+/// Writes log lines to stderr, with any progress bar hidden so lines logged
+/// while tables are exported in parallel aren't drawn over, and to the log file
+struct LogWriter {
+    console: bool,
+    file: Option<RotatingFile>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(file) = &mut self.file {
+            // A full or missing disk shouldn't stop the export, or the console output
+            if let Err(e) = file.write_all(buf) {
+                if self.console {
+                    progress::suspend(|| eprintln!("Unable to write to the log file\n{e}"));
+                }
+            }
+        }
+        if self.console {
+            progress::suspend(|| std::io::stderr().write_all(buf))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        std::io::stderr().flush()
+    }
+}
+
+/// A log file that's renamed to `<path>.1` once it reaches its size limit,
+/// shifting older files up to `<path>.<max_files>` and deleting the oldest
+#[derive(Debug)]
+struct RotatingFile {
+    config: LogFile,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens the log file for appending, creating it and its directory if needed
+    fn open(config: &LogFile) -> std::io::Result<Self> {
+        if let Some(parent) = config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            config: config.clone(),
+            file,
+            size,
+        })
+    }
+
+    /// The path of the `n`th newest rotated file
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.config.path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.config.max_files == 0 {
+            self.file = File::create(&self.config.path)?;
+        } else {
+            let oldest = self.rotated_path(self.config.max_files);
+            if oldest.exists() {
+                std::fs::remove_file(oldest)?;
+            }
+            for n in (1..self.config.max_files).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.config.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.config.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // A line longer than the limit is still written, to an empty file
+        if self.size > 0 && self.size + buf.len() as u64 > self.config.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -4,73 +4,319 @@ mod database;
 // TODO these should be merged
 mod file_helpers;
 mod helpers;
+mod logging;
+mod metrics;
+mod progress;
+mod schedule;
+mod shutdown;
+#[cfg(test)]
+mod test_helpers;
+// Only the S3 store uses the upload helpers outside of tests
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+mod upload;
+mod validate;
+mod verify;
+mod webhook;
 use crate::cli::DuckDBExportOptions;
+use crate::cli::ExportOptions;
+use crate::cli::SqlDialect;
 use clap::Parser;
 use cli::Cli;
+use cli::Command;
 use config::SQLEngineConfig;
+use database::manifest::verify_manifest;
+use database::manifest::ChecksumProblem;
+use database::manifest::Manifest;
+use database::schema::create_schema_statement;
+use database::schema::create_table_statement;
 use database::Database;
+use database::ExportSummary;
+use database::TableOutcome;
+#[cfg(feature = "duckdb")]
+use file_helpers::append_run_log;
+use file_helpers::sanitize_schema;
+#[cfg(feature = "duckdb")]
+use file_helpers::write_parquet_files_to_duckdb_table;
+use helpers::create_snapshot_directory;
+use helpers::display_path;
+use helpers::ensure_free_space;
+use helpers::find_table_files;
+use helpers::find_table_parquets;
+use helpers::format_bytes;
+use helpers::update_latest_symlink;
+use log::{error, info, warn};
+use metrics::MetricsServer;
+use metrics::RunMetrics;
+use schedule::Interval;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::path::Path;
+use std::path::PathBuf;
 use std::process;
 use std::time::Duration;
+use std::time::Instant;
+use webhook::RunReport;
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    cli.export.resolve_run_id();
+    if let Err(e) = logging::init(
+        cli.log_level(),
+        cli.export.run_id(),
+        cli.log_format,
+        cli.log_file().as_ref(),
+        !cli.no_console_log,
+    ) {
+        eprintln!("Unable to open the log file\n{e}");
+        process::exit(1);
+    }
+
+    // Subcommands that don't need the config
+    if let Some(Command::BuildDuckdb { export_directory }) = &cli.command {
+        if !build_duckdb(export_directory, &DuckDBExportOptions::from(&cli.database)) {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::VerifyManifest { manifest }) = &cli.command {
+        if !verify_checksums(manifest) {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(directory) = &cli.from_parquet {
+        let export_directory = match cli.get_export_directory() {
+            Ok(path) => path,
+            Err(e) => {
+                error!("{}", e);
+                process::exit(1);
+            }
+        };
+        let duckdb_options = DuckDBExportOptions::from(&cli.database);
+        if !load_table_files(directory, &export_directory, &duckdb_options) {
+            process::exit(1);
+        }
+        return;
+    }
+
     let config_path = cli.get_config_path();
 
-    match SQLEngineConfig::load(&config_path) {
+    match load_configs(&config_path, &cli.export) {
         Ok(configs) => {
-            let duckdb_options = if cli.database.include_duckdb {
-                Some(DuckDBExportOptions::from(&cli.database))
-            } else {
-                None
+            if let Some(Command::ListTables { json }) = cli.command {
+                if !list_tables(&configs, json) {
+                    process::exit(1);
+                }
+                return;
+            }
+
+            if let Some(Command::Validate) = cli.command {
+                if !validate::validate_all(&configs, cli.startup_concurrency) {
+                    process::exit(1);
+                }
+                return;
+            }
+
+            if let Some(Command::Verify { checksum }) = cli.command {
+                if !verify::verify_all(&configs, checksum) {
+                    process::exit(1);
+                }
+                return;
+            }
+
+            let export_directory = match cli.get_export_directory() {
+                Ok(path) => path,
+                Err(e) => {
+                    error!("{}", e);
+                    process::exit(1);
+                }
             };
 
-            run_and_watch(
+            if cli.estimate_size {
+                estimate_export_size(configs, cli.compression_ratio_estimate);
+                return;
+            }
+
+            if let Some(n) = cli.tail {
+                print_tails(configs, n);
+                return;
+            }
+
+            let duckdb_options = DuckDBExportOptions::from(&cli.database);
+
+            // Fail before writing anything rather than halfway through
+            if (cli.check_disk_space || cli.min_free_mb.is_some()) && !cli.export.dry_run {
+                let estimate = if cli.check_disk_space {
+                    estimate_required_space(
+                        &configs,
+                        &duckdb_options,
+                        cli.compression_ratio_estimate,
+                    )
+                } else {
+                    0
+                };
+                let required =
+                    estimate.max(cli.min_free_mb.unwrap_or(0).saturating_mul(1024 * 1024));
+                if let Err(e) = ensure_free_space(&export_directory, required, |path| {
+                    fs2::available_space(path)
+                }) {
+                    error!("{e}");
+                    process::exit(1);
+                }
+            }
+
+            let metrics = match cli.metrics_addr.map(MetricsServer::start).transpose() {
+                Ok(metrics) => metrics,
+                Err(e) => {
+                    error!("Unable to serve metrics\n{e}");
+                    process::exit(1);
+                }
+            };
+
+            let summary = run_and_watch(
                 configs,
-                &cli.get_export_directory(),
-                duckdb_options.as_ref(),
+                &config_path,
+                &export_directory,
+                &duckdb_options,
                 cli.row_limit,
-                cli.delay,
-            )
+                Interval::from_cli(cli.delay, cli.schedule.as_ref()),
+                &cli.export,
+                metrics.as_ref(),
+            );
+            if summary.has_failures() {
+                process::exit(1);
+            }
         }
         Err(e) => {
-            eprintln!("{}", e);
+            error!("{}", e);
             process::exit(1);
         }
     }
 }
 
+/// Loads and validates the config file.
+///
+/// Databases without their own `include_views` or `query_timeout_secs` setting
+/// follow `--include-views` and `--query-timeout-secs`.
+fn load_configs(
+    config_path: &Path,
+    export_options: &ExportOptions,
+) -> Result<HashMap<String, SQLEngineConfig>, String> {
+    let mut configs = SQLEngineConfig::load(config_path)?;
+    for config in configs.values_mut() {
+        config
+            .include_views
+            .get_or_insert(export_options.include_views);
+        if config.query_timeout_secs.is_none() {
+            config.query_timeout_secs = export_options.query_timeout_secs;
+        }
+    }
+    Ok(configs)
+}
+
 /// Continuously monitors and exports data from multiple database configurations.
 ///
 /// # Arguments
 ///
 /// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `config_path` - The config file, re-read before each run after the first
 /// * `export_directory` - The directory path where exported files will be saved
-/// * `duckdb_options` - Optional DuckDB export configuration
+/// * `duckdb_options` - DuckDB export defaults, which each database may override
 /// * `row_limit` - Optional limit on the number of rows to export per table
-/// * `delay` - Optional delay in seconds between export runs
+/// * `interval` - Optional delay or cron schedule between export runs
+/// * `export_options` - Options controlling how each table is written
+/// * `metrics` - The `--metrics-addr` server, updated after each run
 ///
-/// This function either runs the export once (if no delay is specified) or
-/// continuously with a specified delay between runs. Each run processes all
+/// This function either runs the export once (if no interval is specified) or
+/// continuously, waiting for the delay or the next scheduled time between runs. Each run processes all
 /// configured databases and exports their data to Parquet files.
+/// Changes to the config file take effect on the next run, if the changed
+/// config is invalid the last valid one is used instead.
+/// In watch mode Ctrl-C stops new tables from starting and exits with code 0
+/// once the current tables are written.
+///
+/// # Returns
+///
+/// The summary of the export, this only returns when no interval is specified
+/// or a cron schedule has no more upcoming times
 fn run_and_watch(
-    configs: HashMap<String, SQLEngineConfig>,
+    mut configs: HashMap<String, SQLEngineConfig>,
+    config_path: &Path,
     export_directory: &Path,
-    duckdb_options: Option<&DuckDBExportOptions>,
+    duckdb_options: &DuckDBExportOptions,
     row_limit: Option<u32>,
-    delay: Option<u32>,
-) {
-    match delay {
-        None => run(configs.clone(), export_directory, duckdb_options, row_limit),
-        Some(t) => loop {
-            run(configs.clone(), export_directory, duckdb_options, row_limit);
-            println!("");
-            println!("");
-            println!("Export Completed, waiting {t} Seconds before next Run!");
-            println!("");
-            println!("");
-            std::thread::sleep(Duration::from_secs(t.into()));
+    interval: Option<Interval>,
+    export_options: &ExportOptions,
+    metrics: Option<&MetricsServer>,
+) -> ExportSummary {
+    if interval.is_some() {
+        if let Err(e) = shutdown::install_handler() {
+            warn!("Unable to install the Ctrl-C handler\n{e}");
+        }
+    }
+
+    match interval {
+        None => run(
+            configs.clone(),
+            export_directory,
+            duckdb_options,
+            row_limit,
+            export_options,
+            metrics,
+        ),
+        Some(interval) => loop {
+            let summary = run(
+                configs.clone(),
+                export_directory,
+                duckdb_options,
+                row_limit,
+                export_options,
+                metrics,
+            );
+            // Keep watching, the next run may succeed
+            if summary.has_failures() {
+                error!("Export had failures: {summary}");
+            }
+            if !shutdown::requested() {
+                let Some(wait) = interval.wait(chrono::Local::now()) else {
+                    info!("Export Completed, the schedule has no more runs");
+                    return summary;
+                };
+                match &interval {
+                    Interval::Delay(_) => {
+                        info!(
+                            "Export Completed, waiting {} Seconds before next Run!",
+                            wait.as_secs()
+                        )
+                    }
+                    Interval::Cron(_) => info!(
+                        "Export Completed, next Run at {}",
+                        chrono::Local::now() + wait
+                    ),
+                }
+                shutdown::sleep(wait);
+            }
+            if shutdown::requested() {
+                info!("Shut down cleanly");
+                process::exit(0);
+            }
+
+            // Pick up any changes made to the config since the last run
+            // (a missing file would otherwise be replaced with the default config)
+            if !config_path.exists() {
+                error!(
+                    "Config file {} no longer exists, keeping the last valid one",
+                    display_path(config_path)
+                );
+                continue;
+            }
+            match load_configs(config_path, export_options) {
+                Ok(reloaded) => configs = reloaded,
+                Err(e) => error!("Unable to reload the config, keeping the last valid one\n{e}"),
+            }
         },
     }
     // for (name, config) in configs {
@@ -91,37 +337,571 @@ fn run_and_watch(
 ///
 /// * `configs` - A HashMap of database configurations, keyed by database name
 /// * `export_directory` - The directory path where exported files will be saved
-/// * `duckdb_options` - Optional DuckDB export configuration
+/// * `duckdb_options` - DuckDB export defaults, which each database may override
 /// * `row_limit` - Optional limit on the number of rows to export per table
+/// * `export_options` - Options controlling how each table is written
+/// * `metrics` - The `--metrics-addr` server, which is given this run's metrics
 ///
 /// This function iterates through each database configuration, creates a new database
 /// connection, and exports the data to Parquet files and optionally to DuckDB.
+/// The outcome of every table is written to `manifest.json` in the export directory.
+/// With `--snapshot` everything is written to a new timestamped directory underneath it.
+/// With `--webhook-url` a summary of the run is posted once it has finished.
+///
+/// # Returns
+///
+/// A count of the tables that succeeded and failed across all databases
 fn run(
     configs: HashMap<String, SQLEngineConfig>,
     export_directory: &Path,
-    duckdb_options: Option<&DuckDBExportOptions>,
+    duckdb_options: &DuckDBExportOptions,
     row_limit: Option<u32>,
-) {
+    export_options: &ExportOptions,
+    metrics: Option<&MetricsServer>,
+) -> ExportSummary {
+    let start = Instant::now();
+    let databases = configs.len();
+    let mut manifest = Manifest::new(export_options.run_id());
+    let mut summary = ExportSummary::default();
+
+    let base_directory = export_directory;
+    let snapshot = if export_options.snapshot && !export_options.dry_run {
+        match create_snapshot_directory(base_directory, chrono::Local::now()) {
+            Ok(snapshot) => {
+                info!("Writing snapshot to {}", display_path(&snapshot));
+                Some(snapshot)
+            }
+            Err(e) => {
+                error!(
+                    "Unable to create a snapshot directory in {}\n{e}",
+                    display_path(base_directory)
+                );
+                summary.databases_failed = configs.len();
+                return summary;
+            }
+        }
+    } else {
+        None
+    };
+    let export_directory = snapshot.as_deref().unwrap_or(base_directory);
+    let mut exported_files = Vec::new();
+    let mut ddl = String::new();
+
     for (name, config) in configs {
-        println!("Processing database: {}", name);
+        if shutdown::requested() {
+            break;
+        }
+        info!(database = name.as_str(); "Processing database: {}", name);
 
         // Get the confi
         let config_clone = config.clone();
         // Get the Database Config
-        let db = Database::new(config.clone(), config_clone.database_type);
-        // Get custom row_limit overrides from the toml
-        let override_limits = config.get_override_limits();
+        let db = match Database::new(config.clone(), config_clone.database_type) {
+            Ok(db) => db,
+            Err(e) => {
+                // Carry on with the other databases
+                error!("Skipping {name}: {e}");
+                summary.databases_failed += 1;
+                if export_options.fail_fast {
+                    break;
+                }
+                continue;
+            }
+        };
+        // Get custom row_limit overrides from the toml, then the command line
+        let override_limits = export_options.merge_table_limits(config.get_override_limits());
+        // Apply any per-database format and DuckDB overrides
+        let export_options = &resolve_export_options(&config, export_options);
+        let duckdb_options = resolve_duckdb_options(&config, duckdb_options);
 
         match db.export_dataframes(
             row_limit,
             export_directory,
-            duckdb_options,
+            duckdb_options.as_ref(),
             &name,
             override_limits,
             config.custom_queries,
+            export_options,
+        ) {
+            Ok(outcomes) => {
+                if let Some(log_path) = export_options
+                    .run_log
+                    .as_ref()
+                    .filter(|_| !export_options.dry_run)
+                {
+                    write_run_log(log_path, export_options.run_id(), &name, &outcomes);
+                }
+                exported_files.extend(
+                    outcomes
+                        .iter()
+                        // A DuckDB-only export writes no table files
+                        .filter(|o| o.succeeded() && o.file_path.exists())
+                        .map(|o| o.file_path.clone()),
+                );
+                if let Some(dialect) = export_options.ddl {
+                    ddl.push_str(&database_ddl(dialect, &name, &outcomes));
+                }
+                manifest.add_outcomes(&name, &outcomes);
+                summary.add_outcomes(&outcomes);
+            }
+            Err(e) => {
+                error!("{e}");
+                summary.databases_failed += 1;
+                if export_options.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(metrics) = metrics {
+        metrics.update(RunMetrics::new(&summary, &manifest, start.elapsed()));
+    }
+
+    // A dry run exports nothing, so there is nothing to record
+    if export_options.dry_run {
+        return summary;
+    }
+
+    info!("Export timing: {}", manifest.record_timing(start.elapsed()));
+    let manifest_path = export_directory.join("manifest.json");
+    if let Err(e) = manifest.write(&manifest_path) {
+        error!(
+            "Unable to write manifest {}\n{e}",
+            display_path(&manifest_path)
+        );
+    }
+    let skipped_path = export_directory.join("skipped_tables.json");
+    if let Err(e) = manifest.write_skipped(&skipped_path) {
+        error!(
+            "Unable to write skipped tables {}\n{e}",
+            display_path(&skipped_path)
+        );
+    }
+    if export_options.ddl.is_some() {
+        let ddl_path = export_directory.join("schema.sql");
+        match std::fs::write(&ddl_path, &ddl) {
+            Ok(()) => exported_files.push(ddl_path),
+            Err(e) => error!("Unable to write DDL {}\n{e}", display_path(&ddl_path)),
+        }
+    }
+
+    if let Some(snapshot) = snapshot
+        .as_deref()
+        .filter(|_| export_options.latest_symlink)
+    {
+        if let Err(e) = update_latest_symlink(base_directory, snapshot) {
+            error!(
+                "Unable to update the latest symlink in {}\n{e}",
+                display_path(base_directory)
+            );
+        }
+    }
+
+    if let Some(bucket) = &export_options.s3_bucket {
+        let duckdb_path = export_directory.join(&duckdb_options.file_name);
+        if duckdb_path.is_file() {
+            exported_files.push(duckdb_path);
+        }
+        upload_to_s3(
+            bucket,
+            export_options.s3_prefix.as_deref().unwrap_or_default(),
+            export_directory,
+            &exported_files,
+        );
+    }
+
+    if let Some(url) = &export_options.webhook_url {
+        let report = RunReport::new(
+            export_options.run_id(),
+            databases,
+            &summary,
+            start.elapsed(),
+        );
+        let timeout = Duration::from_secs(export_options.webhook_timeout_secs);
+        if let Err(e) = webhook::notify(url, &report, timeout) {
+            error!("Unable to notify the webhook, the export is unaffected\n{e}");
+        }
+    }
+
+    info!("Export finished: {summary}");
+    summary
+}
+
+/// The `CREATE SCHEMA` and `CREATE TABLE` statements for the tables and custom
+/// queries of a database that were exported, in a schema named like its DuckDB one
+fn database_ddl(dialect: SqlDialect, name: &str, outcomes: &[TableOutcome]) -> String {
+    let schema = sanitize_schema(name);
+    let mut ddl = format!("-- {name}\n{}\n", create_schema_statement(&schema));
+    for outcome in outcomes {
+        if let Some(columns) = outcome.columns.as_ref().filter(|_| outcome.succeeded()) {
+            ddl.push_str(&create_table_statement(
+                dialect,
+                &schema,
+                &outcome.table_name,
+                columns,
+            ));
+            ddl.push('\n');
+        }
+    }
+    ddl
+}
+
+/// Uploads the exported files to S3, reporting but not failing on errors.
+#[allow(unused_variables)]
+fn upload_to_s3(bucket: &str, prefix: &str, export_directory: &Path, files: &[PathBuf]) {
+    if cfg!(feature = "s3") {
+        #[cfg(feature = "s3")]
+        match upload::S3Store::new(bucket) {
+            Ok(store) => {
+                let failed = upload::upload_files(&store, prefix, export_directory, files);
+                if failed > 0 {
+                    error!("{failed} files failed to upload to s3://{bucket}");
+                }
+            }
+            Err(e) => error!("Unable to start the S3 client\n{e}"),
+        }
+    } else {
+        warn!("S3 Feature is Disabled, no files uploaded");
+    }
+}
+
+/// Applies a database's `format` override on top of the CLI's options.
+fn resolve_export_options<'a>(
+    config: &SQLEngineConfig,
+    defaults: &'a ExportOptions,
+) -> Cow<'a, ExportOptions> {
+    match config.format {
+        Some(format) if format != defaults.format => Cow::Owned(ExportOptions {
+            format,
+            ..defaults.clone()
+        }),
+        _ => Cow::Borrowed(defaults),
+    }
+}
+
+/// Applies a database's DuckDB overrides on top of the CLI defaults.
+///
+/// # Returns
+///
+/// `None` if DuckDB should not be built for this database.
+fn resolve_duckdb_options(
+    config: &SQLEngineConfig,
+    defaults: &DuckDBExportOptions,
+) -> Option<DuckDBExportOptions> {
+    if !config.include_duckdb.unwrap_or(defaults.include) {
+        return None;
+    }
+
+    Some(DuckDBExportOptions {
+        include: true,
+        file_name: defaults.file_name.clone(),
+        separator: config
+            .separator
+            .clone()
+            .or_else(|| defaults.separator.clone()),
+        direct_sqlite: defaults.direct_sqlite,
+        views: defaults.views,
+        limits: defaults.limits.clone(),
+        post_load_sql: config.post_load_sql.clone().unwrap_or_default(),
+        post_load_sql_file: config.post_load_sql_file.clone(),
+    })
+}
+
+/// Estimates the disk space an export needs for `--check-disk-space`.
+///
+/// Every table's size in the source database scaled by `compression_ratio`,
+/// ignoring row limits, twice over for databases that build DuckDB as it holds
+/// a copy of the tables. Databases whose sizes can't be read are left out.
+fn estimate_required_space(
+    configs: &HashMap<String, SQLEngineConfig>,
+    duckdb_options: &DuckDBExportOptions,
+    compression_ratio: f64,
+) -> u64 {
+    configs
+        .iter()
+        .map(|(name, config)| {
+            let size: u64 = match Database::new(config.clone(), config.database_type)
+                .and_then(|db| db.get_table_sizes())
+            {
+                Ok(sizes) => sizes.iter().map(|(_, size)| size).sum(),
+                Err(e) => {
+                    warn!("Unable to estimate the size of {name}, it's left out of the disk space check\n{e}");
+                    return 0;
+                }
+            };
+            let copies = match resolve_duckdb_options(config, duckdb_options) {
+                Some(_) => 2,
+                None => 1,
+            };
+            ((size as f64 * compression_ratio) as u64).saturating_mul(copies)
+        })
+        .sum()
+}
+
+/// Prints the projected on-disk size of exporting each database without exporting anything.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `compression_ratio` - The expected ratio of parquet size to source table size
+///
+/// Sizes come from each database's catalog statistics, so they are approximate
+/// and do not account for `--row-limit` or custom queries.
+fn estimate_export_size(configs: HashMap<String, SQLEngineConfig>, compression_ratio: f64) {
+    let mut grand_total = 0.0;
+    for (name, config) in configs {
+        println!("Estimating size of database: {}", name);
+
+        let sizes = match Database::new(config.clone(), config.database_type)
+            .and_then(|db| db.get_table_sizes())
+        {
+            Ok(sizes) => sizes,
+            Err(e) => {
+                error!("Unable to estimate size of {name}: {e}");
+                continue;
+            }
+        };
+
+        let mut total = 0.0;
+        for (table, size) in sizes {
+            let estimate = size as f64 * compression_ratio;
+            total += estimate;
+            println!(
+                "    {table}: {} -> ~{}",
+                format_bytes(size),
+                format_bytes(estimate as u64)
+            );
+        }
+        println!("  Total for {name}: ~{}", format_bytes(total as u64));
+        grand_total += total;
+    }
+    println!(
+        "Estimated export size: ~{}",
+        format_bytes(grand_total as u64)
+    );
+}
+
+/// Prints the tables that would be exported from every database, without exporting anything.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `json` - Print a JSON object of table names keyed by database name
+///
+/// # Returns
+///
+/// `false` if the tables of any database couldn't be listed
+fn list_tables(configs: &HashMap<String, SQLEngineConfig>, json: bool) -> bool {
+    let mut names: Vec<&String> = configs.keys().collect();
+    names.sort();
+
+    let mut all_ok = true;
+    let mut listed: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for name in names {
+        let config = &configs[name];
+        let db = Database::new(config.clone(), config.database_type);
+        let result = match json {
+            true => db.and_then(|db| db.get_export_tables()).map(|mut tables| {
+                tables.sort();
+                listed.insert(name, tables);
+            }),
+            false => {
+                println!("Database: {}", name);
+                db.and_then(|db| db.print_tables())
+            }
+        };
+        if let Err(e) = result {
+            error!("Unable to list the tables of {name}: {e}");
+            all_ok = false;
+        }
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&listed) {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                error!("Unable to serialize the table list\n{e}");
+                all_ok = false;
+            }
+        }
+    }
+    all_ok
+}
+
+/// Recomputes the checksums of the files in a manifest and prints any that are missing or changed.
+///
+/// # Returns
+///
+/// `true` if every file matched its checksum
+fn verify_checksums(manifest_path: &Path) -> bool {
+    let results = match verify_manifest(manifest_path) {
+        Ok(results) => results,
+        Err(e) => {
+            error!("Unable to verify {}\n{e}", display_path(manifest_path));
+            return false;
+        }
+    };
+
+    let mut problems = 0;
+    for result in &results {
+        let table = format!("{}.{}", result.database, result.table);
+        match &result.problem {
+            None => continue,
+            Some(ChecksumProblem::Missing) => {
+                println!("MISSING   {table} {}", display_path(&result.file_path))
+            }
+            Some(ChecksumProblem::Mismatch { expected, actual }) => println!(
+                "MISMATCH  {table} {} (expected {expected}, found {actual})",
+                display_path(&result.file_path)
+            ),
+        }
+        problems += 1;
+    }
+    println!(
+        "{} of {} files verified",
+        results.len() - problems,
+        results.len()
+    );
+    problems == 0
+}
+
+/// Prints the last `n` rows of every table, grouped by database, without exporting anything.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `n` - The number of rows to print from each table
+fn print_tails(configs: HashMap<String, SQLEngineConfig>, n: u32) {
+    for (name, config) in configs {
+        println!("Database: {}", name);
+
+        if let Err(e) = Database::new(config.clone(), config.database_type)
+            .and_then(|db| db.print_all_tables_tail(n))
+        {
+            error!("Unable to print tables of {name}: {e}");
+        }
+    }
+}
+
+/// Builds the DuckDB database from parquet files that were exported by an earlier run.
+///
+/// # Arguments
+///
+/// * `export_directory` - The directory the parquet files were exported to
+/// * `duckdb_options` - DuckDB export configuration, the file is created under `export_directory`
+///
+/// # Returns
+///
+/// `true` if the export directory could be read and every table was loaded
+#[allow(unused_variables)]
+fn build_duckdb(export_directory: &Path, duckdb_options: &DuckDBExportOptions) -> bool {
+    let schemas = match find_table_parquets(export_directory) {
+        Ok(schemas) => schemas,
+        Err(e) => {
+            error!(
+                "Unable to read export directory {}\n{e}",
+                display_path(export_directory)
+            );
+            return false;
+        }
+    };
+
+    let mut complete = true;
+    if cfg!(feature = "duckdb") {
+        #[cfg(feature = "duckdb")]
+        for (schema, tables) in schemas {
+            info!("Loading {} tables into schema: {}", tables.len(), schema);
+            match write_parquet_files_to_duckdb_table(
+                tables,
+                &schema,
+                &export_directory.join(&duckdb_options.file_name),
+                duckdb_options,
+                // Without the config there are no indexes to add
+                &HashMap::new(),
+            ) {
+                Ok(failures) => complete &= failures.is_empty(),
+                Err(e) => {
+                    error!("{e}");
+                    complete = false;
+                }
+            }
+        }
+    } else {
+        info!("Duckdb Feature is Disabled, No database created");
+    }
+    complete
+}
+
+/// Builds the DuckDB database from parquet and CSV files that weren't exported by this tool.
+///
+/// # Arguments
+///
+/// * `directory` - The directory holding the files, each is loaded as a table named after it
+/// * `export_directory` - The directory the DuckDB file is created in
+/// * `duckdb_options` - DuckDB export configuration, the tables are created in the `main` schema
+///
+/// # Returns
+///
+/// `true` if every table was loaded
+#[allow(unused_variables)]
+fn load_table_files(
+    directory: &Path,
+    export_directory: &Path,
+    duckdb_options: &DuckDBExportOptions,
+) -> bool {
+    let tables = match find_table_files(directory) {
+        Ok(tables) if tables.is_empty() => {
+            error!(
+                "No parquet or CSV files found in {}",
+                display_path(directory)
+            );
+            return false;
+        }
+        Ok(tables) => tables,
+        Err(e) => {
+            error!("Unable to read directory {}\n{e}", display_path(directory));
+            return false;
+        }
+    };
+
+    let mut complete = true;
+    if cfg!(feature = "duckdb") {
+        info!(
+            "Loading {} tables from {}",
+            tables.len(),
+            display_path(directory)
+        );
+        #[cfg(feature = "duckdb")]
+        match write_parquet_files_to_duckdb_table(
+            tables,
+            "main",
+            &export_directory.join(&duckdb_options.file_name),
+            duckdb_options,
+            // Without the config there are no indexes to add
+            &HashMap::new(),
         ) {
-            Ok(_) => {}
-            Err(e) => eprintln!("{e}"),
+            Ok(failures) => complete = failures.is_empty(),
+            Err(e) => {
+                error!("{e}");
+                complete = false;
+            }
+        }
+    } else {
+        info!("Duckdb Feature is Disabled, No database created");
+    }
+    complete
+}
+
+/// Appends the outcomes of a database's export to the run-log, reporting but not failing on errors.
+#[allow(unused_variables)]
+fn write_run_log(log_path: &Path, run_id: &str, database: &str, outcomes: &[TableOutcome]) {
+    if cfg!(feature = "duckdb") {
+        #[cfg(feature = "duckdb")]
+        if let Err(e) = append_run_log(log_path, run_id, database, outcomes) {
+            error!("Unable to write run log {}\n{e}", display_path(log_path));
         }
+    } else {
+        warn!("Duckdb Feature is Disabled, the run log requires it");
     }
 }
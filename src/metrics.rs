@@ -0,0 +1,238 @@
+use crate::database::manifest::Manifest;
+use crate::database::ExportSummary;
+use log::{debug, error, info};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{ExportStatus, TableOutcome};
+    use crate::helpers::TableParquet;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        let server = MetricsServer::start("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = server.local_addr();
+
+        // After one run
+        let outcome = |name: &str, status: ExportStatus| {
+            let tp = TableParquet {
+                file_path: PathBuf::from(format!("{name}.parquet")),
+                table_name: name.to_string(),
+            };
+            TableOutcome::new(&tp, status, Duration::from_millis(10))
+        };
+        let outcomes = [
+            outcome("notes", ExportStatus::Success { rows: 30 }),
+            outcome("tags", ExportStatus::Success { rows: 12 }),
+            outcome("users", ExportStatus::Failed("bad".to_string())),
+        ];
+        let mut summary = ExportSummary::default();
+        summary.add_outcomes(&outcomes);
+        let mut manifest = Manifest::new("run");
+        manifest.add_outcomes("Joplin \"prod\"", &outcomes);
+        server.update(RunMetrics::new(
+            &summary,
+            &manifest,
+            Duration::from_millis(2500),
+        ));
+
+        let response = get(addr, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        for name in [
+            "database_exporter_last_run_timestamp_seconds",
+            "database_exporter_tables_exported 2",
+            "database_exporter_tables_failed 1",
+            "database_exporter_run_duration_seconds 2.5",
+            "database_exporter_rows_exported{database=\"Joplin \\\"prod\\\"\"} 42",
+        ] {
+            assert!(response.contains(name), "{name} missing from\n{response}");
+        }
+        assert!(response.contains("# TYPE database_exporter_tables_exported gauge"));
+
+        assert!(get(addr, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
+
+/// The prefix of every metric name
+const PREFIX: &str = "database_exporter";
+
+/// How long a scrape may take to send its request before it's dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The metrics of the last export run, served in the Prometheus text format
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunMetrics {
+    /// When the run finished, as a Unix timestamp, 0 before the first run
+    pub last_run_timestamp: i64,
+    pub tables_exported: usize,
+    /// Tables and custom queries that failed or panicked
+    pub tables_failed: usize,
+    pub run_duration_secs: f64,
+    /// The rows written, keyed by the database's name in the config
+    pub rows_by_database: BTreeMap<String, usize>,
+}
+
+impl RunMetrics {
+    /// The metrics of a run that has just finished
+    ///
+    /// * `summary` - The counts of the tables that succeeded and failed
+    /// * `manifest` - The tables exported, for the rows of each database
+    /// * `duration` - The wall-clock time of the run
+    pub fn new(summary: &ExportSummary, manifest: &Manifest, duration: Duration) -> Self {
+        let mut rows_by_database = BTreeMap::new();
+        for table in &manifest.tables {
+            *rows_by_database.entry(table.database.clone()).or_default() +=
+                table.rows.unwrap_or_default();
+        }
+        Self {
+            last_run_timestamp: chrono::Utc::now().timestamp(),
+            tables_exported: summary.succeeded,
+            tables_failed: summary.failed + summary.panicked,
+            run_duration_secs: duration.as_secs_f64(),
+            rows_by_database,
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, samples: Vec<(String, String)>| {
+            out.push_str(&format!("# HELP {PREFIX}_{name} {help}\n"));
+            out.push_str(&format!("# TYPE {PREFIX}_{name} gauge\n"));
+            for (labels, value) in samples {
+                out.push_str(&format!("{PREFIX}_{name}{labels} {value}\n"));
+            }
+        };
+        gauge(
+            "last_run_timestamp_seconds",
+            "When the last export run finished, as a Unix timestamp",
+            vec![(String::new(), self.last_run_timestamp.to_string())],
+        );
+        gauge(
+            "tables_exported",
+            "Tables and custom queries exported by the last run",
+            vec![(String::new(), self.tables_exported.to_string())],
+        );
+        gauge(
+            "tables_failed",
+            "Tables and custom queries that failed or panicked in the last run",
+            vec![(String::new(), self.tables_failed.to_string())],
+        );
+        gauge(
+            "run_duration_seconds",
+            "How long the last export run took",
+            vec![(String::new(), self.run_duration_secs.to_string())],
+        );
+        gauge(
+            "rows_exported",
+            "Rows written for each database by the last run",
+            self.rows_by_database
+                .iter()
+                .map(|(database, rows)| {
+                    (
+                        format!("{{database=\"{}\"}}", escape_label(database)),
+                        rows.to_string(),
+                    )
+                })
+                .collect(),
+        );
+        out
+    }
+}
+
+/// Escapes a label value, which is written in double quotes
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A minimal HTTP server serving the metrics of the last run on `/metrics`
+#[derive(Debug)]
+pub struct MetricsServer {
+    metrics: Arc<Mutex<RunMetrics>>,
+    local_addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Binds `addr` and serves the metrics from a background thread, requests
+    /// are answered one at a time
+    pub fn start(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let metrics = Arc::new(Mutex::new(RunMetrics::default()));
+        let served = Arc::clone(&metrics);
+        std::thread::Builder::new()
+            .name("metrics".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    let result = stream.and_then(|stream| respond(stream, &served));
+                    if let Err(e) = result {
+                        debug!("Unable to serve a metrics request\n{e}");
+                    }
+                }
+            })?;
+        info!("Serving metrics on http://{local_addr}/metrics");
+        Ok(Self {
+            metrics,
+            local_addr,
+        })
+    }
+
+    /// The address the server is listening on, with the port it was given if `addr` had port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Replaces the metrics served with those of the latest run
+    pub fn update(&self, metrics: RunMetrics) {
+        match self.metrics.lock() {
+            Ok(mut current) => *current = metrics,
+            Err(e) => error!("Unable to update the metrics\n{e}"),
+        }
+    }
+}
+
+/// Answers a single request, the metrics for `GET /metrics` and 404 for anything else
+fn respond(stream: TcpStream, metrics: &Mutex<RunMetrics>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers aren't needed, but are read so the client isn't reset
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = match request_line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["GET", "/metrics"] => (
+            "200 OK",
+            metrics.lock().unwrap_or_else(|e| e.into_inner()).render(),
+        ),
+        _ => ("404 Not Found", "Not Found\n".to_string()),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
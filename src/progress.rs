@@ -0,0 +1,107 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::Mutex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_suppressed_without_terminal() {
+        assert!(progress_enabled(true, true, false));
+        // Redirected output and CI never get a bar, even when requested
+        assert!(!progress_enabled(true, false, false));
+        assert!(!progress_enabled(true, true, true));
+        assert!(!progress_enabled(false, true, false));
+
+        let progress = TableProgress::new(3, "db", progress_enabled(true, false, false));
+        assert!(progress.bar.is_none());
+        progress.start("notes");
+        progress.finish_table();
+        assert_eq!(suspend(|| 1 + 1), 2);
+    }
+}
+
+/// The bar being drawn, if any, so log lines can be written around it
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Whether a progress bar should be drawn.
+///
+/// # Arguments
+///
+/// * `requested` - Whether `--progress` was passed
+/// * `is_terminal` - Whether stdout is a terminal, a redirected bar is just noise
+/// * `ci` - Whether the `CI` environment variable is set, CI logs capture every redraw
+pub fn progress_enabled(requested: bool, is_terminal: bool, ci: bool) -> bool {
+    requested && is_terminal && !ci
+}
+
+/// Runs `f` with the progress bar hidden, so anything it writes to the
+/// terminal isn't drawn over. Without a bar `f` is just run.
+pub fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    let bar = ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match bar {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
+
+/// A progress bar of the tables and custom queries exported from a database,
+/// showing how many are done and the one started most recently.
+///
+/// The bar is cleared when this is dropped. A disabled bar does nothing.
+#[derive(Debug)]
+pub struct TableProgress {
+    bar: Option<ProgressBar>,
+}
+
+impl TableProgress {
+    /// Starts a bar of `total` tables, if `--progress` was passed and it can be drawn
+    pub fn from_options(total: usize, database: &str, requested: bool) -> Self {
+        let enabled = progress_enabled(
+            requested,
+            std::io::stdout().is_terminal(),
+            std::env::var_os("CI").is_some(),
+        );
+        Self::new(total, database, enabled)
+    }
+
+    /// Starts a bar of `total` tables, or a disabled one that draws nothing
+    pub fn new(total: usize, database: &str, enabled: bool) -> Self {
+        if !enabled {
+            return Self { bar: None };
+        }
+        let bar = ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stdout());
+        if let Ok(style) =
+            ProgressStyle::with_template("{prefix} [{elapsed_precise}] {bar:40} {pos}/{len} {msg}")
+        {
+            bar.set_style(style);
+        }
+        bar.set_prefix(database.to_string());
+        *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(bar.clone());
+        Self { bar: Some(bar) }
+    }
+
+    /// Shows the table that was just started
+    pub fn start(&self, table: &str) {
+        if let Some(bar) = &self.bar {
+            bar.set_message(table.to_string());
+        }
+    }
+
+    /// Counts a table as done, whether or not it succeeded
+    pub fn finish_table(&self) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        }
+    }
+}
+
+impl Drop for TableProgress {
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Local, TimeZone};
+use cron::Schedule;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_next_run_nightly() {
+        let schedule = parse_schedule("0 0 2 * * *").unwrap();
+
+        let before = Utc.with_ymd_and_hms(2025, 1, 1, 1, 30, 0).unwrap();
+        assert_eq!(
+            next_run(&schedule, &before),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 2, 0, 0).unwrap())
+        );
+
+        // At or after 2am, the next run is tomorrow
+        let exactly = Utc.with_ymd_and_hms(2025, 1, 1, 2, 0, 0).unwrap();
+        assert_eq!(
+            next_run(&schedule, &exactly),
+            Some(Utc.with_ymd_and_hms(2025, 1, 2, 2, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_next_run_every_fifteen_minutes() {
+        let schedule = parse_schedule("0 */15 * * * *").unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 3, 10, 9, 7, 12).unwrap();
+        let times: Vec<_> = schedule.after(&after).take(3).collect();
+        assert_eq!(
+            times,
+            [
+                Utc.with_ymd_and_hms(2025, 3, 10, 9, 15, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 3, 10, 9, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 3, 10, 9, 45, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_schedule_is_rejected() {
+        assert!(parse_schedule("every night").is_err());
+    }
+}
+
+/// When the next export starts in watch mode
+#[derive(Debug, Clone)]
+pub enum Interval {
+    /// A fixed delay after each run finishes (`--delay`)
+    Delay(Duration),
+    /// The next time matching a cron expression, in local time (`--schedule`)
+    Cron(Schedule),
+}
+
+impl Interval {
+    /// Chooses the interval from `--delay` or `--schedule`, `None` runs the export once
+    pub fn from_cli(delay: Option<u32>, schedule: Option<&Schedule>) -> Option<Self> {
+        match (delay, schedule) {
+            (_, Some(schedule)) => Some(Self::Cron(schedule.clone())),
+            (Some(t), None) => Some(Self::Delay(Duration::from_secs(t.into()))),
+            (None, None) => None,
+        }
+    }
+
+    /// How long to wait after a run that finished at `now` before starting the next one.
+    ///
+    /// # Returns
+    ///
+    /// `None` if a cron schedule has no more upcoming times
+    pub fn wait(&self, now: DateTime<Local>) -> Option<Duration> {
+        match self {
+            Self::Delay(delay) => Some(*delay),
+            Self::Cron(schedule) => {
+                let next = next_run(schedule, &now)?;
+                Some((next - now).to_std().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// The first time after `after` that matches `schedule`
+pub fn next_run<Tz: TimeZone>(schedule: &Schedule, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    schedule.after(after).next()
+}
+
+/// Parses a `--schedule` cron expression, e.g. `0 0 2 * * *` for 2am every day.
+///
+/// The fields are `sec min hour day-of-month month day-of-week [year]`.
+pub fn parse_schedule(value: &str) -> Result<Schedule, String> {
+    Schedule::from_str(value).map_err(|e| format!("invalid cron expression '{value}': {e}"))
+}
@@ -0,0 +1,33 @@
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Set once Ctrl-C has been pressed
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a Ctrl-C handler that requests a graceful shutdown rather than aborting.
+///
+/// Tables that are already being exported are finished, but no new tables are started.
+pub fn install_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        info!("Interrupt received, finishing the current tables before shutting down");
+        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+    })
+}
+
+/// Whether Ctrl-C has been pressed
+pub fn requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Sleeps for `duration`, waking early if a shutdown is requested.
+pub fn sleep(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while !requested() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        std::thread::sleep(remaining.min(Duration::from_millis(250)));
+    }
+}
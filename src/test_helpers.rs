@@ -0,0 +1,90 @@
+//! Fixtures shared by the unit tests.
+//!
+//! Everything is created under a `TempDir`, which is removed when it's dropped,
+//! so a failing assertion doesn't leave files behind.
+
+use crate::cli::DuckDBExportOptions;
+use crate::config::SQLEngineConfig;
+use crate::database::types::DatabaseType;
+use crate::database::Database;
+use crate::file_helpers::DuckDBLimits;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A new, empty, directory under the system's temp directory
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("database_exporter_")
+        .tempdir()
+        .unwrap()
+}
+
+/// A SQLite database created from a SQL script, in its own temporary directory
+pub struct SqliteFixture {
+    dir: TempDir,
+    path: PathBuf,
+}
+
+impl SqliteFixture {
+    /// Creates the database by running `sql`, which may be empty
+    pub fn new(sql: &str) -> Self {
+        let dir = temp_dir();
+        let path = dir.path().join("source.sqlite");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(sql)
+            .unwrap();
+        Self { dir, path }
+    }
+
+    /// The temporary directory holding the database, exports can be written under it
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The SQLite file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A fresh connection, to change the database between exports
+    pub fn connection(&self) -> rusqlite::Connection {
+        rusqlite::Connection::open(&self.path).unwrap()
+    }
+
+    /// The config of the database, `extra` is TOML that follows the connection settings
+    pub fn config(&self, extra: &str) -> SQLEngineConfig {
+        toml::from_str(&format!(
+            r#"
+            database_type = "sqlite"
+            database = "{}"
+            username = ""
+            password = ""
+            host = ""
+            port = ""
+            {extra}
+            "#,
+            self.path.display()
+        ))
+        .unwrap()
+    }
+
+    /// The database with the config from `config`
+    pub fn database(&self, extra: &str) -> Database {
+        Database::new(self.config(extra), DatabaseType::SQLite).unwrap()
+    }
+}
+
+/// Building `database.duckdb` from the parquet files with the default settings
+pub fn duckdb_options() -> DuckDBExportOptions {
+    DuckDBExportOptions {
+        include: true,
+        file_name: "database.duckdb".to_string(),
+        separator: None,
+        direct_sqlite: false,
+        views: false,
+        limits: DuckDBLimits::default(),
+        post_load_sql: Vec::new(),
+        post_load_sql_file: None,
+    }
+}
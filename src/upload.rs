@@ -0,0 +1,187 @@
+use crate::helpers::display_path;
+use log::{error, info};
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::temp_dir;
+    use std::sync::Mutex;
+
+    /// Records the keys put, failing any whose file name is `fail`
+    #[derive(Default)]
+    struct MockStore {
+        keys: Mutex<Vec<String>>,
+    }
+
+    impl ObjectStore for MockStore {
+        fn put_file(&self, key: &str, path: &Path) -> Result<(), String> {
+            if path.file_stem().is_some_and(|s| s == "fail") {
+                return Err("Access Denied".to_string());
+            }
+            self.keys.lock().unwrap().push(key.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_upload_files_keys() {
+        let dir = temp_dir();
+        let schema = dir.path().join("chinook");
+        let partitioned = schema.join("invoice");
+        std::fs::create_dir_all(&partitioned).unwrap();
+        for file in [
+            schema.join("album.parquet"),
+            schema.join("fail.parquet"),
+            partitioned.join("year=2024.parquet"),
+            dir.path().join("database.duckdb"),
+        ] {
+            std::fs::write(file, b"").unwrap();
+        }
+
+        let store = MockStore::default();
+        let failed = upload_files(
+            &store,
+            "exports/nightly/",
+            dir.path(),
+            &[
+                schema.join("album.parquet"),
+                schema.join("fail.parquet"),
+                partitioned,
+                dir.path().join("database.duckdb"),
+            ],
+        );
+
+        assert_eq!(failed, 1);
+        assert_eq!(
+            store.keys.into_inner().unwrap(),
+            [
+                "exports/nightly/chinook/album.parquet",
+                "exports/nightly/chinook/invoice/year=2024.parquet",
+                "exports/nightly/database.duckdb",
+            ]
+        );
+        assert_eq!(
+            object_key("", Path::new("/export"), Path::new("/export/a/b.parquet")),
+            "a/b.parquet"
+        );
+    }
+}
+
+/// Somewhere exported files can be copied to, e.g. an S3 bucket
+pub trait ObjectStore {
+    /// Uploads the file at `path` under `key`
+    fn put_file(&self, key: &str, path: &Path) -> Result<(), String>;
+}
+
+/// The object key of an exported file, its path relative to the export directory under `prefix`.
+///
+/// Keys always use `/`, whatever the platform's path separator.
+pub fn object_key(prefix: &str, export_directory: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(export_directory).unwrap_or(path);
+    let relative = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    match prefix.trim_matches('/') {
+        "" => relative,
+        prefix => format!("{prefix}/{relative}"),
+    }
+}
+
+/// Uploads each exported file, reporting any that fail without stopping the others.
+///
+/// Directories, i.e. partitioned tables, are uploaded file by file.
+///
+/// # Arguments
+///
+/// * `store` - Where to upload the files
+/// * `prefix` - Prepended to every key, e.g. `exports/nightly`
+/// * `export_directory` - The directory keys are made relative to
+/// * `files` - The exported files and directories
+///
+/// # Returns
+///
+/// The number of files that failed to upload
+pub fn upload_files(
+    store: &dyn ObjectStore,
+    prefix: &str,
+    export_directory: &Path,
+    files: &[PathBuf],
+) -> usize {
+    let mut failed = 0;
+    for path in files.iter().flat_map(|f| expand_directory(f)) {
+        let key = object_key(prefix, export_directory, &path);
+        match store.put_file(&key, &path) {
+            Ok(()) => info!("Uploaded {} to {key}", display_path(&path)),
+            Err(e) => {
+                error!("Unable to upload {} to {key}\n{e}", display_path(&path));
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// The files underneath `path`, or `path` itself if it's a file
+fn expand_directory(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .flat_map(|entry| expand_directory(&entry.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// An S3 bucket, using the credentials and region of the default AWS config chain
+#[cfg(feature = "s3")]
+pub struct S3Store {
+    bucket: String,
+    client: aws_sdk_s3::Client,
+    runtime: tokio::runtime::Runtime,
+}
+
+#[cfg(feature = "s3")]
+impl S3Store {
+    pub fn new(bucket: &str) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let config = runtime.block_on(aws_config::load_defaults(
+            aws_config::BehaviorVersion::latest(),
+        ));
+        Ok(Self {
+            bucket: bucket.to_string(),
+            client: aws_sdk_s3::Client::new(&config),
+            runtime,
+        })
+    }
+}
+
+#[cfg(feature = "s3")]
+impl ObjectStore for S3Store {
+    fn put_file(&self, key: &str, path: &Path) -> Result<(), String> {
+        self.runtime.block_on(async {
+            let body = aws_sdk_s3::primitives::ByteStream::from_path(path)
+                .await
+                .map_err(|e| e.to_string())?;
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| aws_sdk_s3::error::DisplayErrorContext(e).to_string())?;
+            Ok(())
+        })
+    }
+}
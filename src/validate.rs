@@ -0,0 +1,183 @@
+use crate::config::SQLEngineConfig;
+use crate::database::Database;
+use crate::database::DatabaseError;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::SqliteFixture;
+    use std::time::Duration;
+
+    /// The config of a SQLite database whose directory doesn't exist
+    fn missing_config(fixture: &SqliteFixture) -> SQLEngineConfig {
+        let mut config = fixture.config("");
+        config.database = fixture
+            .dir()
+            .join("missing")
+            .join("bad.sqlite")
+            .display()
+            .to_string();
+        config
+    }
+
+    #[test]
+    fn test_check_databases_one_at_a_time() {
+        // An empty database
+        let fixture = SqliteFixture::new("");
+
+        let configs = HashMap::from([
+            ("Good".to_string(), fixture.config("")),
+            ("Bad".to_string(), missing_config(&fixture)),
+            ("Also good".to_string(), fixture.config("")),
+        ]);
+        let results = check_databases(&configs, NonZeroUsize::new(1));
+        let unbounded = check_databases(&configs, None);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(*results[&"Good".to_string()].as_ref().unwrap(), 0);
+        assert_eq!(*results[&"Also good".to_string()].as_ref().unwrap(), 0);
+        assert!(results[&"Bad".to_string()].is_err());
+        assert_eq!(unbounded.len(), 3);
+
+        // The most checks running at once never exceeds the concurrency
+        let configs: HashMap<String, SQLEngineConfig> = (0..6)
+            .map(|i| (format!("Database {i}"), fixture.config("")))
+            .collect();
+        for concurrency in [1, 2] {
+            let running = AtomicUsize::new(0);
+            let peak = AtomicUsize::new(0);
+            let results =
+                check_databases_with(&configs, NonZeroUsize::new(concurrency), |config| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    check_database(config)
+                });
+            assert_eq!(results.len(), 6);
+            assert!(peak.load(Ordering::SeqCst) <= concurrency);
+        }
+    }
+
+    #[test]
+    fn test_validate_sqlite() {
+        // An empty database
+        let fixture = SqliteFixture::new("");
+        let good = fixture.config("");
+        let bad = missing_config(&fixture);
+
+        let good_result = check_database(&good);
+        let bad_result = check_database(&bad);
+
+        let configs = HashMap::from([("Good".to_string(), good.clone()), ("Bad".to_string(), bad)]);
+        let all_ok = validate_all(&configs, NonZeroUsize::new(1));
+        let only_good = HashMap::from([("Good".to_string(), good)]);
+        let good_ok = validate_all(&only_good, None);
+
+        assert_eq!(good_result.unwrap(), 0);
+        assert!(bad_result.is_err());
+        assert!(!all_ok);
+        assert!(good_ok);
+    }
+}
+
+/// Connects to every configured database and lists its tables, without exporting anything.
+///
+/// The databases are checked with `check_databases` and a table of the results
+/// is printed once they have all finished.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `concurrency` - The most databases to connect to at once, all of them if `None`
+///
+/// # Returns
+///
+/// `true` if every database was reachable
+pub fn validate_all(
+    configs: &HashMap<String, SQLEngineConfig>,
+    concurrency: Option<NonZeroUsize>,
+) -> bool {
+    let results = check_databases(configs, concurrency);
+    let mut names: Vec<&String> = configs.keys().collect();
+    names.sort();
+
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0).max(8);
+    println!("{:width$}  Status  Detail", "Database");
+    let mut all_ok = true;
+    for name in &names {
+        match &results[name] {
+            Ok(tables) => println!("{name:width$}  OK      {tables} tables"),
+            Err(e) => {
+                all_ok = false;
+                let first_line = e.to_string();
+                let first_line = first_line.lines().next().unwrap_or_default();
+                println!("{name:width$}  FAIL    {first_line}");
+            }
+        }
+    }
+
+    all_ok
+}
+
+/// Connects to every configured database and lists its tables.
+///
+/// Databases are checked in parallel by at most `concurrency` worker threads,
+/// so a large config doesn't open a connection to every database at once.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `concurrency` - The most databases to connect to at once, all of them if `None`
+///
+/// # Returns
+///
+/// The number of tables found in each database, keyed by database name
+pub fn check_databases(
+    configs: &HashMap<String, SQLEngineConfig>,
+    concurrency: Option<NonZeroUsize>,
+) -> HashMap<&String, Result<usize, DatabaseError>> {
+    check_databases_with(configs, concurrency, check_database)
+}
+
+/// `check_databases`, checking each database with `check`
+fn check_databases_with<'a>(
+    configs: &'a HashMap<String, SQLEngineConfig>,
+    concurrency: Option<NonZeroUsize>,
+    check: impl Fn(&SQLEngineConfig) -> Result<usize, DatabaseError> + Sync,
+) -> HashMap<&'a String, Result<usize, DatabaseError>> {
+    let names: Vec<&String> = configs.keys().collect();
+
+    // Each worker takes the next unchecked database until none are left
+    let workers = concurrency.map_or(names.len(), |n| n.get().min(names.len()));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(HashMap::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(name) = names.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let result = check(&configs[*name]);
+                    results
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(*name, result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Connects to a database and runs its tables query.
+///
+/// # Returns
+///
+/// The number of tables found
+fn check_database(config: &SQLEngineConfig) -> Result<usize, DatabaseError> {
+    let db = Database::new(config.clone(), config.database_type)?;
+    Ok(db.get_table_names()?.len())
+}
@@ -0,0 +1,160 @@
+use crate::config::SQLEngineConfig;
+use crate::database::Database;
+use crate::database::DatabaseError;
+use log::{error, info, warn};
+use polars::frame::DataFrame;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    #[test]
+    fn test_checksum_ignores_row_order() {
+        let a = df!("id" => [1, 2, 3], "name" => ["a", "b", "c"]).unwrap();
+        let b = df!("id" => [3i64, 1, 2], "name" => ["c", "a", "b"]).unwrap();
+        let c = df!("id" => [1, 2, 3], "name" => ["a", "b", "d"]).unwrap();
+        assert_eq!(dataframe_checksum(&a), dataframe_checksum(&b));
+        assert_ne!(dataframe_checksum(&a), dataframe_checksum(&c));
+    }
+
+    #[test]
+    fn test_checksum_keeps_values_apart() {
+        // The same characters split differently between the columns
+        let a = df!("a" => ["ab"], "b" => ["c"]).unwrap();
+        let b = df!("a" => ["a"], "b" => ["bc"]).unwrap();
+        assert_ne!(dataframe_checksum(&a), dataframe_checksum(&b));
+        assert_eq!(dataframe_checksum(&a).len(), 64);
+    }
+}
+
+/// Compares every database that has `verify_against` set with its target.
+///
+/// # Arguments
+///
+/// * `configs` - A HashMap of database configurations, keyed by database name
+/// * `checksum` - Also compare a hash of every row, this reads both tables in full
+///
+/// # Returns
+///
+/// `true` if every pair matched
+pub fn verify_all(configs: &HashMap<String, SQLEngineConfig>, checksum: bool) -> bool {
+    let mut pairs = 0;
+    let mut matched = 0;
+    for (name, config) in configs {
+        let Some(target_name) = &config.verify_against else {
+            continue;
+        };
+        // Validated when the config was loaded
+        let target_config = &configs[target_name];
+        pairs += 1;
+
+        info!("Verifying {name} against {target_name}");
+        let result = Database::new(config.clone(), config.database_type).and_then(|source| {
+            let target = Database::new(target_config.clone(), target_config.database_type)?;
+            verify_pair(&source, &target, checksum)
+        });
+        match result {
+            Ok(discrepancies) if discrepancies.is_empty() => {
+                matched += 1;
+                info!("{name} matches {target_name}");
+            }
+            Ok(discrepancies) => {
+                error!(
+                    "{} discrepancies between {name} and {target_name}:",
+                    discrepancies.len()
+                );
+                for discrepancy in discrepancies {
+                    error!("  {discrepancy}");
+                }
+            }
+            Err(e) => {
+                error!("Unable to verify {name} against {target_name}: {e}");
+            }
+        }
+    }
+
+    if pairs == 0 {
+        warn!("No databases have `verify_against` set, nothing to verify");
+    }
+    println!("{matched} of {pairs} databases match their target");
+
+    matched == pairs
+}
+
+/// Compares the tables of two databases.
+///
+/// # Returns
+///
+/// A description of each discrepancy found, empty if the databases match
+fn verify_pair(
+    source: &Database,
+    target: &Database,
+    checksum: bool,
+) -> Result<Vec<String>, DatabaseError> {
+    let source_tables: BTreeSet<String> = source.get_table_names()?.into_iter().collect();
+    let target_tables: BTreeSet<String> = target.get_table_names()?.into_iter().collect();
+
+    let mut discrepancies = vec![];
+    for table in source_tables.difference(&target_tables) {
+        discrepancies.push(format!("{table}: missing from target"));
+    }
+    for table in target_tables.difference(&source_tables) {
+        discrepancies.push(format!("{table}: missing from source"));
+    }
+
+    for table in source_tables.intersection(&target_tables) {
+        let source_rows = source.count_rows(table)?;
+        let target_rows = target.count_rows(table)?;
+        if source_rows != target_rows {
+            discrepancies.push(format!(
+                "{table}: {source_rows} rows in source, {target_rows} in target"
+            ));
+            continue;
+        }
+
+        if checksum {
+            let source_hash = dataframe_checksum(&source.get_dataframe(table, None)?);
+            let target_hash = dataframe_checksum(&target.get_dataframe(table, None)?);
+            if source_hash != target_hash {
+                discrepancies.push(format!("{table}: row contents differ"));
+            }
+        }
+    }
+
+    Ok(discrepancies)
+}
+
+/// Hashes the rows of a DataFrame with SHA-256, independent of their order.
+///
+/// Each row is hashed from the display form of its values, so integer and float
+/// widths that differ between databases don't affect the result. Every value is
+/// prefixed with its length so a value can't run into the next. The row hashes
+/// are then sorted before being combined.
+///
+/// # Returns
+///
+/// The hex SHA-256 of the sorted row hashes
+fn dataframe_checksum(df: &DataFrame) -> String {
+    let mut row_hashes: Vec<[u8; 32]> = (0..df.height())
+        .map(|i| {
+            let mut hasher = Sha256::new();
+            for value in df.get(i).unwrap_or_default() {
+                let value = value.to_string();
+                hasher.update((value.len() as u64).to_le_bytes());
+                hasher.update(value.as_bytes());
+            }
+            hasher.finalize().into()
+        })
+        .collect();
+    row_hashes.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for row_hash in &row_hashes {
+        hasher.update(row_hash);
+    }
+    format!("{:x}", hasher.finalize())
+}
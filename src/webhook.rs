@@ -0,0 +1,183 @@
+use crate::database::ExportSummary;
+use log::{info, warn};
+use serde::Serialize;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves one request per status in `statuses`, returning the bodies received
+    fn mock_webhook(statuses: Vec<u16>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    let lower = line.to_lowercase();
+                    if let Some(length) = lower.strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Mock\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn test_webhook_posts_run_report() {
+        let summary = ExportSummary {
+            succeeded: 5,
+            failed: 1,
+            databases_failed: 1,
+            ..Default::default()
+        };
+        let report = RunReport::new("nightly", 3, &summary, Duration::from_millis(1500));
+
+        // A server error is retried, the second attempt succeeds
+        let (url, server) = mock_webhook(vec![503, 200]);
+        let result = notify_with_backoff(
+            &url,
+            &report,
+            Duration::from_secs(5),
+            Duration::from_millis(1),
+        );
+        assert_eq!(result, Ok(()));
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        let body: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "run_id": "nightly",
+                "databases": 3,
+                "databases_failed": 1,
+                "tables_succeeded": 5,
+                "tables_failed": 1,
+                "tables_panicked": 0,
+                "duckdb_failed": 0,
+                "duration_secs": 1.5,
+                "success": false,
+            })
+        );
+
+        // A client error isn't retried
+        let (url, server) = mock_webhook(vec![404]);
+        let result = notify_with_backoff(
+            &url,
+            &report,
+            Duration::from_secs(5),
+            Duration::from_millis(1),
+        );
+        assert!(result.unwrap_err().contains("404"));
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
+
+/// The number of times a request is retried after the webhook responds with a 5xx
+const MAX_RETRIES: u32 = 2;
+
+/// The delay before the first retry, doubled for each retry after it
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// The JSON body posted to `--webhook-url` after each run
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a> {
+    pub run_id: &'a str,
+    /// Databases in the config, including any that failed
+    pub databases: usize,
+    pub databases_failed: usize,
+    pub tables_succeeded: usize,
+    pub tables_failed: usize,
+    pub tables_panicked: usize,
+    /// Tables that were exported but couldn't be loaded into DuckDB
+    pub duckdb_failed: usize,
+    pub duration_secs: f64,
+    /// Whether nothing failed, see `ExportSummary::has_failures`
+    pub success: bool,
+}
+
+impl<'a> RunReport<'a> {
+    pub fn new(
+        run_id: &'a str,
+        databases: usize,
+        summary: &ExportSummary,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            run_id,
+            databases,
+            databases_failed: summary.databases_failed,
+            tables_succeeded: summary.succeeded,
+            tables_failed: summary.failed,
+            tables_panicked: summary.panicked,
+            duckdb_failed: summary.duckdb_failed,
+            duration_secs: duration.as_secs_f64(),
+            success: !summary.has_failures(),
+        }
+    }
+}
+
+/// Posts `report` to `url` as JSON, retrying with exponential backoff while
+/// the webhook responds with a server error.
+///
+/// Other responses of 400 or above, and connection errors, are returned straight away.
+///
+/// # Arguments
+///
+/// * `url` - The webhook to notify
+/// * `report` - The summary of the run
+/// * `timeout` - How long each attempt may take, from connecting to reading the response
+pub fn notify(url: &str, report: &RunReport, timeout: Duration) -> Result<(), String> {
+    notify_with_backoff(url, report, timeout, RETRY_BASE_DELAY)
+}
+
+fn notify_with_backoff(
+    url: &str,
+    report: &RunReport,
+    timeout: Duration,
+    base_delay: Duration,
+) -> Result<(), String> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let mut retry = 0;
+    loop {
+        match agent.post(url).send_json(report) {
+            Ok(response) => {
+                info!("Notified {url} ({})", response.status());
+                return Ok(());
+            }
+            Err(ureq::Error::Status(status, _)) if status >= 500 && retry < MAX_RETRIES => {
+                let delay = base_delay.saturating_mul(2u32.pow(retry));
+                retry += 1;
+                warn!(
+                    "{url} responded with {status}, retrying in {delay:?} (attempt {retry} of {MAX_RETRIES})"
+                );
+                std::thread::sleep(delay);
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                return Err(format!(
+                    "{url} responded with {status} {}",
+                    response.status_text()
+                ))
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! The fixture shared by the integration tests: an empty SQLite database and a
+//! `config.toml` that exports it
+// Each test file compiles its own copy of this module and uses only part of it
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+pub struct Fixture {
+    dir: TempDir,
+    database: PathBuf,
+    config: PathBuf,
+}
+
+impl Fixture {
+    /// Creates a temporary directory holding an empty SQLite database
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        // An empty file is a valid, empty, SQLite database
+        let database = dir.path().join("empty.sqlite");
+        std::fs::File::create(&database).unwrap();
+        let config = dir.path().join("config.toml");
+        Fixture {
+            dir,
+            database,
+            config,
+        }
+    }
+
+    /// The default export directory, inside the temporary directory
+    pub fn export_directory(&self) -> PathBuf {
+        self.dir.path().join("export")
+    }
+
+    /// The config of a database `name` reading the empty SQLite database, with
+    /// `extra` lines appended to its table
+    pub fn database(&self, name: &str, extra: &str) -> String {
+        format!(
+            r#"
+["{name}"]
+database_type = "sqlite"
+database = "{}"
+username = ""
+password = ""
+host = ""
+port = ""
+{extra}
+"#,
+            self.database.display()
+        )
+    }
+
+    /// Writes `contents` to `config.toml`
+    pub fn write_config(&self, contents: &str) {
+        std::fs::write(&self.config, contents).unwrap();
+    }
+
+    /// A quiet command exporting the config to `export_directory`
+    pub fn command(&self, export_directory: &Path) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_extract_to_sqlite_rs"));
+        command
+            .arg("--config")
+            .arg(&self.config)
+            .arg("--export-directory")
+            .arg(export_directory)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+}
+
+/// The config of a custom query `name` of the database `database`
+pub fn custom_query(database: &str, name: &str, query: &str) -> String {
+    format!(
+        r#"
+[["{database}".custom_queries]]
+name = "{name}"
+description = "A query under test"
+query = "{query}"
+"#
+    )
+}
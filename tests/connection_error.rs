@@ -0,0 +1,32 @@
+//! Checks that a database with a bad connection string doesn't stop the others exporting
+
+mod common;
+
+use common::{custom_query, Fixture};
+
+#[test]
+fn test_bad_connection_skips_only_that_database() {
+    // A reachable SQLite database with a single custom query
+    let fixture = Fixture::new();
+    fixture.write_config(
+        &(fixture.database("Good", "")
+            + &custom_query("Good", "one", "SELECT 1 AS one")
+            + r#"
+["Unreachable"]
+database_type = "postgres"
+database = "chinook"
+username = "postgres"
+password = "postgres"
+host = "not a valid host"
+port = "5432"
+"#),
+    );
+
+    let export_directory = fixture.export_directory();
+    let status = fixture.command(&export_directory).status().unwrap();
+
+    let exported = export_directory.join("good").join("one.parquet").exists();
+    // The unreachable database is reported as a failure
+    assert_eq!(status.code(), Some(1));
+    assert!(exported);
+}
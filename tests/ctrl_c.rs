@@ -0,0 +1,53 @@
+//! Checks that Ctrl-C stops watch mode cleanly
+#![cfg(unix)]
+
+mod common;
+
+use common::Fixture;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_sigint_exits_cleanly_in_watch_mode() {
+    let fixture = Fixture::new();
+    fixture.write_config(&fixture.database("Empty", ""));
+
+    let export_directory = fixture.export_directory();
+    let mut child = fixture
+        .command(&export_directory)
+        .args(["--delay", "3600"])
+        .spawn()
+        .unwrap();
+
+    // The handler is installed before the first run, whose manifest is written
+    // just before it starts sleeping
+    let manifest = export_directory.join("manifest.json");
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !manifest.exists() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("The first run did not finish");
+        }
+        sleep(Duration::from_millis(100));
+    }
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("Process did not exit after SIGINT");
+        }
+        sleep(Duration::from_millis(100));
+    };
+
+    assert_eq!(status.code(), Some(0));
+}
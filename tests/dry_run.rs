@@ -0,0 +1,23 @@
+//! Checks that `--dry-run` doesn't create the export directory
+
+mod common;
+
+use common::{custom_query, Fixture};
+
+#[test]
+fn test_dry_run_creates_no_directories() {
+    let fixture = Fixture::new();
+    fixture.write_config(
+        &(fixture.database("Notes", "") + &custom_query("Notes", "one", "SELECT 1 AS one")),
+    );
+
+    let export_directory = fixture.export_directory().join("nested");
+    let status = fixture
+        .command(&export_directory)
+        .args(["--duckdb-file-name", "duckdb/database.duckdb", "--dry-run"])
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(0));
+    assert!(!fixture.export_directory().exists());
+}
@@ -0,0 +1,23 @@
+//! Checks that a run exits with code 1 when any table or custom query failed
+
+mod common;
+
+use common::{custom_query, Fixture};
+
+/// Exports an empty SQLite database with a single custom query, returning the exit code
+fn export_custom_query(query: &str) -> Option<i32> {
+    let fixture = Fixture::new();
+    fixture.write_config(&(fixture.database("Empty", "") + &custom_query("Empty", "query", query)));
+
+    fixture
+        .command(&fixture.export_directory())
+        .status()
+        .unwrap()
+        .code()
+}
+
+#[test]
+fn test_failed_custom_query_exits_non_zero() {
+    assert_eq!(export_custom_query("SELECT 1 AS one"), Some(0));
+    assert_eq!(export_custom_query("SELECT * FROM missing"), Some(1));
+}
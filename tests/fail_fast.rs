@@ -0,0 +1,33 @@
+//! Checks that `--fail-fast` stops the run at the first failure, before DuckDB is built
+
+mod common;
+
+use common::{custom_query, Fixture};
+
+#[test]
+fn test_fail_fast_skips_duckdb() {
+    // A custom query that can't succeed, followed by one that can
+    let fixture = Fixture::new();
+    fixture.write_config(
+        &(fixture.database("Empty", "")
+            + &custom_query("Empty", "a_missing", "SELECT * FROM missing")
+            + &custom_query("Empty", "b_after", "SELECT 1 AS one")),
+    );
+
+    let export_directory = fixture.export_directory();
+    let status = fixture
+        .command(&export_directory)
+        .arg("--fail-fast")
+        .status()
+        .unwrap();
+
+    assert_eq!(status.code(), Some(1));
+    // Nothing after the failed query is exported
+    assert!(!export_directory
+        .join("empty")
+        .join("b_after.parquet")
+        .exists());
+    // Without the duckdb feature there is never a DuckDB file to skip
+    #[cfg(feature = "duckdb")]
+    assert!(!export_directory.join("database.duckdb").exists());
+}
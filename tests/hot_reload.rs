@@ -0,0 +1,63 @@
+//! Checks that watch mode picks up changes to the config file between runs
+#![cfg(unix)]
+
+mod common;
+
+use common::{custom_query, Fixture};
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// A database exporting a single custom query
+fn database_config(fixture: &Fixture, name: &str) -> String {
+    fixture.database(name, "") + &custom_query(name, "one", "SELECT 1 AS one")
+}
+
+/// Waits up to 30 seconds for `path` to exist
+fn wait_for(path: &Path) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while Instant::now() < deadline {
+        if path.exists() {
+            return true;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn test_config_is_reloaded_in_watch_mode() {
+    let fixture = Fixture::new();
+    fixture.write_config(&database_config(&fixture, "First"));
+
+    let export_directory = fixture.export_directory();
+    let mut child = fixture
+        .command(&export_directory)
+        .args(["--delay", "1"])
+        .spawn()
+        .unwrap();
+
+    let first_exported = wait_for(&export_directory.join("first").join("one.parquet"));
+
+    // An invalid config is ignored, the next run still exports "First"
+    fixture.write_config("not = [valid");
+    sleep(Duration::from_secs(2));
+    let survived_invalid = child.try_wait().unwrap().is_none();
+
+    // A database added to the config is exported on a later run
+    fixture
+        .write_config(&(database_config(&fixture, "First") + &database_config(&fixture, "Second")));
+    let second_exported = wait_for(&export_directory.join("second").join("one.parquet"));
+
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+
+    assert!(first_exported);
+    assert!(survived_invalid);
+    assert!(second_exported);
+    assert_eq!(status.code(), Some(0));
+}
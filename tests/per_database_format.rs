@@ -0,0 +1,35 @@
+//! Checks that a database's `format` in the config overrides `--format`
+
+mod common;
+
+use common::{custom_query, Fixture};
+
+#[test]
+fn test_databases_export_in_their_own_format() {
+    let fixture = Fixture::new();
+    fixture.write_config(
+        &(fixture.database("Default", "")
+            + &custom_query("Default", "one", "SELECT 1 AS one")
+            + &fixture.database("Spreadsheet", r#"format = "csv""#)
+            + &custom_query("Spreadsheet", "one", "SELECT 1 AS one")),
+    );
+
+    let export_directory = fixture.export_directory();
+    let status = fixture
+        .command(&export_directory)
+        .args(["--format", "parquet"])
+        .status()
+        .unwrap();
+
+    let files = |schema: &str| {
+        let mut files: Vec<String> = std::fs::read_dir(export_directory.join(schema))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        files
+    };
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(files("default"), ["one.parquet"]);
+    assert_eq!(files("spreadsheet"), ["one.csv"]);
+}